@@ -0,0 +1,108 @@
+//! Trains a one-block Transformer to predict the next character of a short
+//! text, then samples from it greedily.
+//!
+//! Run with `cargo run --release --example char_lm`.
+
+use mkgrad::{
+    nn::{Embedding, Linear, Module, Rng, TransformerBlock},
+    no_grad,
+    optim::{Adam, Optimizer},
+    Tensor,
+};
+use ndarray::{Array, ArrayD};
+
+const TEXT: &str = "the quick brown fox jumps over the lazy dog. ";
+const CONTEXT: usize = 16;
+const DIM: usize = 32;
+
+struct Model {
+    tokens: Embedding<f64>,
+    positions: Embedding<f64>,
+    block: TransformerBlock<f64>,
+    head: Linear<f64>,
+}
+
+impl Model {
+    fn new(vocabulary: usize, rng: &mut Rng) -> Self {
+        Model {
+            tokens: Embedding::new(vocabulary, DIM, rng),
+            positions: Embedding::new(CONTEXT, DIM, rng),
+            block: TransformerBlock::new(DIM, 4, 4 * DIM, true, rng),
+            head: Linear::new(DIM, vocabulary, true, rng),
+        }
+    }
+
+    /// Maps `[batch, length]` character ids to `[batch, length, vocabulary]`
+    /// logits.
+    fn logits(&self, ids: &ArrayD<usize>) -> Tensor<f64> {
+        let length = ids.shape()[1];
+        let positions = Array::from_shape_fn((1, length), |(_, t)| t).into_dyn();
+        let x = &self.tokens.lookup(ids) + &self.positions.lookup(&positions);
+        self.head.forward(&self.block.forward(&x))
+    }
+
+    fn parameters(&self) -> Vec<Tensor<f64>> {
+        [
+            self.tokens.parameters(),
+            self.positions.parameters(),
+            self.block.parameters(),
+            self.head.parameters(),
+        ]
+        .concat()
+    }
+}
+
+fn main() {
+    let text: Vec<char> = TEXT.chars().collect();
+    let mut vocabulary = text.clone();
+    vocabulary.sort();
+    vocabulary.dedup();
+    let ids: Vec<usize> = text
+        .iter()
+        .map(|c| vocabulary.binary_search(c).unwrap())
+        .collect();
+
+    // Every window of the text, wrapping around at the end.
+    let windows = ids.len();
+    let at = |i: usize| ids[i % ids.len()];
+    let inputs = Array::from_shape_fn((windows, CONTEXT), |(i, t)| at(i + t)).into_dyn();
+    let targets = Array::from_shape_fn((windows * CONTEXT, 1), |(i, _)| {
+        at(i / CONTEXT + i % CONTEXT + 1)
+    })
+    .into_dyn();
+
+    let model = Model::new(vocabulary.len(), &mut Rng::new(0));
+    let mut optimizer = Adam::new(model.parameters(), 0.01);
+    for step in 0..=300 {
+        optimizer.zero_grad();
+        let logits = model
+            .logits(&inputs)
+            .reshape(&[windows * CONTEXT, vocabulary.len()]);
+        let log_probabilities = logits.log_softmax(1);
+        let loss = -log_probabilities.gather(1, &targets).mean(None, false);
+        loss.backward();
+        optimizer.step();
+        if step % 50 == 0 {
+            println!("step {step:3}: loss {:.4}", loss.value()[[]]);
+        }
+    }
+
+    let _guard = no_grad();
+    let mut sample: Vec<usize> = ids[..CONTEXT].to_vec();
+    for _ in 0..2 * TEXT.len() {
+        let window = &sample[sample.len() - CONTEXT..];
+        let input = Array::from_shape_vec((1, CONTEXT), window.to_vec())
+            .unwrap()
+            .into_dyn();
+        let logits = model.logits(&input).value();
+        let last = logits.slice(ndarray::s![0, CONTEXT - 1, ..]);
+        let next = (0..vocabulary.len())
+            .max_by(|&a, &b| last[a].total_cmp(&last[b]))
+            .unwrap();
+        sample.push(next);
+    }
+    println!(
+        "{}",
+        sample.iter().map(|&id| vocabulary[id]).collect::<String>()
+    );
+}
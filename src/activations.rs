@@ -0,0 +1,260 @@
+//! Activation functions commonly used in neural networks.
+
+use num::{traits::FloatConst, Float, One, Zero};
+
+use crate::{value::constant, Elementwise, Var};
+
+/// Computes the logistic sigmoid without overflowing for large `|x|`.
+fn sigmoid<T: Float>(x: T) -> T {
+    if x >= T::zero() {
+        (T::one() + (-x).exp()).recip()
+    } else {
+        let e = x.exp();
+        e / (T::one() + e)
+    }
+}
+
+/// Computes `ln(1 + e^x)` without overflowing for large `x`.
+fn softplus<T: Float>(x: T) -> T {
+    x.max(T::zero()) + (-x.abs()).exp().ln_1p()
+}
+
+/// Computes the error function, with a relative error below `1.2e-7`.
+///
+/// Uses the Chebyshev approximation of the complementary error function from
+/// Numerical Recipes, as `num::Float` doesn't provide one.
+fn erf<T: Float>(x: T) -> T {
+    let z = x.abs();
+    let t = (T::one() + z * constant(0.5)).recip();
+    let coefficients = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ];
+    let polynomial = coefficients
+        .iter()
+        .rev()
+        .fold(T::zero(), |acc, &c| acc * t + constant(c));
+    let erfc = t * (-z * z + polynomial).exp();
+
+    if x >= T::zero() {
+        T::one() - erfc
+    } else {
+        erfc - T::one()
+    }
+}
+
+impl<T: Elementwise + 'static> Var<T> {
+    /// Computes `max(0, x)`.
+    pub fn relu(&self) -> Self {
+        self.unary(
+            |x| x.max(T::Elem::zero()),
+            |x, _| {
+                if x > T::Elem::zero() {
+                    T::Elem::one()
+                } else {
+                    T::Elem::zero()
+                }
+            },
+        )
+    }
+
+    /// Computes `x` for positive inputs and `negative_slope * x` otherwise.
+    pub fn leaky_relu(&self, negative_slope: T::Elem) -> Self {
+        self.unary(
+            move |x| {
+                if x > T::Elem::zero() {
+                    x
+                } else {
+                    negative_slope * x
+                }
+            },
+            move |x, _| {
+                if x > T::Elem::zero() {
+                    T::Elem::one()
+                } else {
+                    negative_slope
+                }
+            },
+        )
+    }
+
+    /// Computes `x` for positive inputs and `alpha * (e^x - 1)` otherwise.
+    pub fn elu(&self, alpha: T::Elem) -> Self {
+        self.unary(
+            move |x| {
+                if x > T::Elem::zero() {
+                    x
+                } else {
+                    alpha * x.exp_m1()
+                }
+            },
+            move |x, y| {
+                if x > T::Elem::zero() {
+                    T::Elem::one()
+                } else {
+                    y + alpha
+                }
+            },
+        )
+    }
+
+    /// Computes the exact Gaussian error linear unit, `x * Φ(x)`.
+    pub fn gelu(&self) -> Self
+    where
+        T::Elem: FloatConst,
+    {
+        let half = constant::<T::Elem>(0.5);
+        self.unary(
+            move |x| half * x * (T::Elem::one() + erf(x * T::Elem::FRAC_1_SQRT_2())),
+            move |x, _| {
+                let cdf = half * (T::Elem::one() + erf(x * T::Elem::FRAC_1_SQRT_2()));
+                let pdf = (-half * x * x).exp()
+                    * T::Elem::FRAC_1_SQRT_2()
+                    * T::Elem::FRAC_2_SQRT_PI()
+                    * half;
+                cdf + x * pdf
+            },
+        )
+    }
+
+    /// Computes the tanh approximation of the Gaussian error linear unit.
+    pub fn gelu_tanh(&self) -> Self
+    where
+        T::Elem: FloatConst,
+    {
+        let half = constant::<T::Elem>(0.5);
+        let cubic = constant::<T::Elem>(0.044715);
+        let scale = (T::Elem::FRAC_2_PI()).sqrt();
+        self.unary(
+            move |x| half * x * (T::Elem::one() + (scale * (x + cubic * x * x * x)).tanh()),
+            move |x, _| {
+                let inner = scale * (x + cubic * x * x * x);
+                let tanh = inner.tanh();
+                let inner_derivative =
+                    scale * (T::Elem::one() + constant::<T::Elem>(3.0) * cubic * x * x);
+                half * (T::Elem::one() + tanh)
+                    + half * x * (T::Elem::one() - tanh * tanh) * inner_derivative
+            },
+        )
+    }
+
+    /// Computes the sigmoid linear unit, `x * sigmoid(x)`.
+    pub fn silu(&self) -> Self {
+        self.unary(
+            |x| x * sigmoid(x),
+            |x, _| {
+                let s = sigmoid(x);
+                s * (T::Elem::one() + x * (T::Elem::one() - s))
+            },
+        )
+    }
+
+    /// Alias of [`Var::silu`].
+    pub fn swish(&self) -> Self {
+        self.silu()
+    }
+
+    /// Computes the logistic sigmoid, `1 / (1 + e^-x)`.
+    pub fn sigmoid(&self) -> Self {
+        self.unary(sigmoid, |_, y| y * (T::Elem::one() - y))
+    }
+
+    /// Computes `ln(1 + e^x)`.
+    pub fn softplus(&self) -> Self {
+        self.unary(softplus, |x, _| sigmoid(x))
+    }
+
+    /// Clamps the value to `[min, max]`.
+    pub fn hardtanh(&self, min: T::Elem, max: T::Elem) -> Self {
+        self.unary(
+            move |x| x.max(min).min(max),
+            move |x, _| {
+                if x > min && x < max {
+                    T::Elem::one()
+                } else {
+                    T::Elem::zero()
+                }
+            },
+        )
+    }
+
+    /// Computes `x * tanh(softplus(x))`.
+    pub fn mish(&self) -> Self {
+        self.unary(
+            |x| x * softplus(x).tanh(),
+            |x, _| {
+                let tanh = softplus(x).tanh();
+                tanh + x * (T::Elem::one() - tanh * tanh) * sigmoid(x)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::check_gradients;
+
+    const INPUTS: [f64; 6] = [-3.1, -1.2, -0.4, 0.3, 1.1, 2.7];
+
+    fn check_activation(f: impl Fn(&Var<f64>) -> Var<f64>) {
+        for input in INPUTS {
+            check_gradients(&[input], |x| f(&x[0]));
+        }
+    }
+
+    #[test]
+    fn finite_differences_rectifiers() {
+        check_activation(Var::relu);
+        check_activation(|x| x.leaky_relu(0.01));
+        check_activation(|x| x.elu(1.5));
+        check_activation(|x| x.hardtanh(-2.0, 2.0));
+    }
+
+    #[test]
+    fn finite_differences_smooth() {
+        check_activation(Var::gelu);
+        check_activation(Var::gelu_tanh);
+        check_activation(Var::silu);
+        check_activation(Var::sigmoid);
+        check_activation(Var::softplus);
+        check_activation(Var::mish);
+    }
+
+    #[test]
+    fn stable_for_large_inputs() {
+        for x in [-1000.0, 1000.0] {
+            let x = Var::new(x);
+            for result in [x.sigmoid(), x.softplus(), x.silu(), x.mish()] {
+                result.backward();
+                assert!(result.value().is_finite());
+            }
+            assert!(x.gradient().is_finite());
+        }
+        assert_eq!(Var::new(1000.0).softplus().value(), 1000.0);
+        assert_eq!(Var::new(-1000.0).sigmoid().value(), 0.0);
+    }
+
+    #[test]
+    fn erf_values() {
+        assert!((erf(0.5_f64) - 0.520_499_877_813_046_5).abs() < 1e-7);
+        assert!((erf(-1.5_f64) + 0.966_105_146_475_310_7).abs() < 1e-7);
+        assert!(erf(0.0_f64).abs() < 1e-7);
+    }
+
+    #[test]
+    fn gelu_approximations_agree() {
+        for input in INPUTS {
+            let x = Var::new(input);
+            assert!((x.gelu().value() - x.gelu_tanh().value()).abs() < 1e-3);
+        }
+    }
+}
@@ -1,105 +1,624 @@
 use std::{
-    cell::RefCell,
+    cell::{Ref, RefCell, RefMut},
+    collections::HashSet,
     fmt::Debug,
-    ops::{AddAssign, Mul, MulAssign},
-    rc::Rc,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+    rc::{Rc, Weak},
 };
 
-use num::traits::{MulAdd, One, Zero};
+use crate::Value;
 
-#[derive(Clone)]
-struct GradFn<'a, T: Clone>(&'a dyn Fn(&Rc<RefCell<Differentiable<'a, T>>>) -> Vec<T>);
+type BackwardFn<T> = dyn Fn(&Differentiable<T>) -> Vec<T>;
 
-impl<T: Clone> Debug for GradFn<'_, T> {
+/// The backward rule of a node: given the node, returns the gradient to push
+/// into each of its children, in the same order as `children`.
+///
+/// Rules are owned closures, so they are free to capture constants, shapes or
+/// any other state saved during the forward pass.
+struct GradFn<T: Clone>(Box<BackwardFn<T>>);
+
+impl<T: Clone> Debug for GradFn<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("GradFn")
     }
 }
 
 /// Represents a differentiable value of a given type.
-#[derive(Clone, Debug)]
-struct Differentiable<'a, T: Clone> {
+#[derive(Debug)]
+pub struct Differentiable<T: Clone> {
     /// The value of the differentiable.
     pub value: T,
     /// The gradient of the differentiable.
     pub gradient: T,
     /// The Differentiable values that were used to compute this Differentiable.
-    children: Vec<Rc<RefCell<Differentiable<'a, T>>>>,
+    children: Vec<Var<T>>,
     /// The function to compute the gradient of the children.
-    grad_fn: GradFn<'a, T>,
+    grad_fn: GradFn<T>,
+    /// Whether `children` and `grad_fn` were released by a backward pass that
+    /// didn't retain the graph.
+    freed: bool,
+    /// The leaves reached by a freeing backward pass started at this node, so
+    /// that [`Var::zero_grad_graph`] can still reset them.
+    freed_leaves: Vec<Weak<RefCell<Differentiable<T>>>>,
 }
 
-fn topology_sort<'a, T: Clone>(
-    diff: &Rc<RefCell<Differentiable<'a, T>>>,
-) -> Vec<Rc<RefCell<Differentiable<'a, T>>>> {
-    let mut result = Vec::new();
+/// A shared handle to a [`Differentiable`] node in the computation graph.
+///
+/// Cloning a `Var` is cheap and yields another handle to the same node, so a
+/// value can be used by any number of operations and the gradients from every
+/// use accumulate into it.
+#[derive(Clone, Debug)]
+pub struct Var<T: Clone>(Rc<RefCell<Differentiable<T>>>);
 
-    for child in diff.borrow().children.iter() {
-        result.append(&mut topology_sort(child));
+impl<T: Clone> Var<T> {
+    /// Creates a new leaf with the given value and a zero gradient.
+    pub fn new(value: T) -> Self
+    where
+        T: Value,
+    {
+        Var::from(Differentiable::from(value))
     }
 
-    result.push(diff.clone());
+    /// Returns a copy of the value of the node.
+    pub fn value(&self) -> T {
+        self.0.borrow().value.clone()
+    }
 
-    result
+    /// Returns a copy of the gradient accumulated in the node.
+    pub fn gradient(&self) -> T {
+        self.0.borrow().gradient.clone()
+    }
+
+    /// Immutably borrows the underlying node.
+    pub fn borrow(&self) -> Ref<'_, Differentiable<T>> {
+        self.0.borrow()
+    }
+
+    /// Mutably borrows the underlying node.
+    pub fn borrow_mut(&self) -> RefMut<'_, Differentiable<T>> {
+        self.0.borrow_mut()
+    }
+
+    /// Returns true if both handles point to the same node.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Identifies the node this handle points to.
+    fn id(&self) -> *const RefCell<Differentiable<T>> {
+        Rc::as_ptr(&self.0)
+    }
+
+    /// Returns true if the node was not computed from other nodes.
+    pub fn is_leaf(&self) -> bool {
+        let diff = self.0.borrow();
+        diff.children.is_empty() && !diff.freed
+    }
+
+    /// Returns a new leaf with the same value, through which no gradient
+    /// flows back to this node.
+    pub fn detach(&self) -> Self
+    where
+        T: Value,
+    {
+        Var::new(self.value())
+    }
+
+    /// Resets the gradient of the node to zero.
+    pub fn zero_grad(&self)
+    where
+        T: Value,
+    {
+        let mut diff = self.0.borrow_mut();
+        diff.gradient = diff.value.zeros_like();
+    }
+
+    /// Back-propagates from this node, accumulating gradients into every leaf
+    /// it was computed from.
+    ///
+    /// The gradients of intermediate nodes are recomputed from scratch on every
+    /// call, while leaves keep accumulating until they are reset.
+    ///
+    /// The root is seeded with a gradient of ones, so for non-scalar values this
+    /// differentiates the sum of the elements.
+    ///
+    /// The graph is kept, so this can be called again; see
+    /// [`Var::backward_with`] to free it instead.
+    pub fn backward(&self)
+    where
+        T: Value,
+    {
+        self.backward_with(true)
+    }
+
+    /// Back-propagates like [`Var::backward`]. Unless `retain_graph` is set,
+    /// every intermediate node then drops its children and backward rule, so
+    /// the memory held by the graph is released as soon as the handles to
+    /// intermediate values are dropped. Values and gradients stay available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the graph below this node has already been freed.
+    pub fn backward_with(&self, retain_graph: bool)
+    where
+        T: Value,
+    {
+        backward(self, retain_graph)
+    }
+
+    /// Resets the gradient of this node and of every node it was computed
+    /// from, leaves included.
+    ///
+    /// After [`Var::backward_with(false)`](Var::backward_with) the root of that
+    /// pass still knows its leaves, but an intermediate node freed by it no
+    /// longer reaches the leaves below it.
+    pub fn zero_grad_graph(&self)
+    where
+        T: Value,
+    {
+        for diff in topology_sort(self) {
+            diff.zero_grad();
+            let leaves = diff.borrow().freed_leaves.clone();
+            for leaf in leaves.iter().filter_map(Weak::upgrade) {
+                Var(leaf).zero_grad();
+            }
+        }
+    }
+}
+
+impl<T: Clone + 'static> Var<T> {
+    /// Creates the result of an operation on `children`.
+    ///
+    /// `grad_fn` receives the new node and returns the gradient for each child,
+    /// in the same order as `children`. Under [`no_grad`](crate::no_grad) the
+    /// result is a leaf instead, and `children` and `grad_fn` are dropped.
+    pub fn from_op<F>(value: T, children: Vec<Var<T>>, grad_fn: F) -> Self
+    where
+        T: Value,
+        F: Fn(&Differentiable<T>) -> Vec<T> + 'static,
+    {
+        if !crate::is_grad_enabled() {
+            return Var::new(value);
+        }
+
+        Var::from(Differentiable {
+            gradient: value.zeros_like(),
+            value,
+            children,
+            grad_fn: GradFn(Box::new(grad_fn)),
+            freed: false,
+            freed_leaves: Vec::new(),
+        })
+    }
 }
 
-fn backward<'a, T: One + Clone + AddAssign>(differentiable: &Rc<RefCell<Differentiable<'a, T>>>) {
-    differentiable.borrow_mut().gradient += T::one();
+impl<T: Clone> From<Differentiable<T>> for Var<T> {
+    fn from(diff: Differentiable<T>) -> Self {
+        Var(Rc::new(RefCell::new(diff)))
+    }
+}
+
+impl<T: Value> From<T> for Var<T> {
+    fn from(value: T) -> Self {
+        Var::new(value)
+    }
+}
+
+/// Orders the nodes reachable from `root` so that every node comes after all of
+/// the nodes it was computed from. Each node appears exactly once.
+///
+/// The traversal uses an explicit stack so arbitrarily deep graphs don't
+/// overflow the call stack.
+fn topology_sort<T: Clone>(root: &Var<T>) -> Vec<Var<T>> {
+    let mut result = Vec::new();
+    let mut visited = HashSet::new();
+    // Each entry is a node and whether its children have already been pushed.
+    let mut stack = vec![(root.clone(), false)];
+
+    while let Some((diff, expanded)) = stack.pop() {
+        if expanded {
+            result.push(diff);
+            continue;
+        }
+        if !visited.insert(diff.id()) {
+            continue;
+        }
+
+        let children = diff.borrow().children.clone();
+        stack.push((diff, true));
+        for child in children {
+            if !visited.contains(&child.id()) {
+                stack.push((child, false));
+            }
+        }
+    }
+
+    result
+}
 
+fn backward<T: Value>(differentiable: &Var<T>, retain_graph: bool) {
     let sorted = topology_sort(differentiable);
+    assert!(
+        sorted.iter().all(|diff| !diff.borrow().freed),
+        "backward through a graph that has already been freed; \
+         use `backward_with(true)` to keep the graph for another backward pass"
+    );
+
+    for diff in sorted.iter().filter(|diff| !diff.is_leaf()) {
+        diff.zero_grad();
+    }
+
+    if !retain_graph && !differentiable.is_leaf() {
+        differentiable.borrow_mut().freed_leaves = sorted
+            .iter()
+            .filter(|diff| diff.is_leaf())
+            .map(|diff| Rc::downgrade(&diff.0))
+            .collect();
+    }
+
+    let seed = differentiable.borrow().value.ones_like();
+    differentiable.borrow_mut().gradient += &seed;
 
     for diff in sorted.iter().rev() {
-        let children = (diff.borrow().grad_fn.0)(diff);
-        for (child, grad) in diff.borrow().children.iter().zip(children.iter()) {
-            child.borrow_mut().gradient += grad.clone();
+        {
+            let diff = diff.borrow();
+            let children = (diff.grad_fn.0)(&diff);
+            for (child, grad) in diff.children.iter().zip(children.iter()) {
+                child.borrow_mut().gradient += grad;
+            }
+        }
+        if !retain_graph && !diff.is_leaf() {
+            diff.borrow_mut().free();
+        }
+    }
+}
+
+impl<T: Clone> Differentiable<T> {
+    /// Returns the nodes this node was computed from.
+    pub fn children(&self) -> &[Var<T>] {
+        &self.children
+    }
+
+    /// Drops the children and backward rule of the node, and everything the
+    /// rule captured.
+    fn free(&mut self) {
+        self.children = Vec::new();
+        self.grad_fn = GradFn(Box::new(|_| Vec::new()));
+        self.freed = true;
+    }
+}
+
+impl<T: Value> Differentiable<T> {
+    /// Sums a gradient with the shape of this node's value down to the shape of
+    /// its `index`th child, undoing any broadcasting done in the forward pass.
+    pub(crate) fn unbroadcast(&self, gradient: T, index: usize) -> T {
+        gradient.sum_to(&self.children[index].borrow().value)
+    }
+}
+
+impl<T: Clone> Drop for Differentiable<T> {
+    /// Releases the graph below this node iteratively, as the default recursive
+    /// drop would overflow the stack on long chains.
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.children);
+
+        while let Some(child) = stack.pop() {
+            if let Ok(cell) = Rc::try_unwrap(child.0) {
+                stack.append(&mut cell.into_inner().children);
+            }
         }
     }
 }
 
-impl<T: Clone + Zero> From<T> for Differentiable<'_, T> {
+impl<T: Value> From<T> for Differentiable<T> {
     fn from(value: T) -> Self {
         Differentiable {
+            gradient: value.zeros_like(),
             value,
-            gradient: T::zero(),
             children: Vec::new(),
-            grad_fn: GradFn(&|_| Vec::new()),
+            grad_fn: GradFn(Box::new(|_| Vec::new())),
+            freed: false,
+            freed_leaves: Vec::new(),
         }
     }
 }
 
-impl<'a, T: Clone + Zero + MulAssign + Mul<Output = T>> Mul<T> for Differentiable<'a, T> {
-    type Output = Self;
+impl<T: Value + Add<Output = T> + 'static> Add<&Var<T>> for &Var<T> {
+    type Output = Var<T>;
 
-    fn mul(self, rhs: T) -> Self::Output {
-        let new_rhs = Differentiable::from(rhs);
+    fn add(self, rhs: &Var<T>) -> Self::Output {
+        Var::from_op(
+            self.value() + rhs.value(),
+            vec![self.clone(), rhs.clone()],
+            |diff| {
+                vec![
+                    diff.unbroadcast(diff.gradient.clone(), 0),
+                    diff.unbroadcast(diff.gradient.clone(), 1),
+                ]
+            },
+        )
+    }
+}
+
+impl<T: Value + Add<Output = T> + 'static> Add<T> for &Var<T> {
+    type Output = Var<T>;
+
+    fn add(self, rhs: T) -> Self::Output {
+        Var::from_op(self.value() + rhs, vec![self.clone()], |diff| {
+            vec![diff.unbroadcast(diff.gradient.clone(), 0)]
+        })
+    }
+}
+
+impl<T: Value + Sub<Output = T> + 'static> Sub<&Var<T>> for &Var<T> {
+    type Output = Var<T>;
+
+    fn sub(self, rhs: &Var<T>) -> Self::Output {
+        Var::from_op(
+            self.value() - rhs.value(),
+            vec![self.clone(), rhs.clone()],
+            |diff| {
+                vec![
+                    diff.unbroadcast(diff.gradient.clone(), 0),
+                    diff.unbroadcast(diff.gradient.zeros_like() - diff.gradient.clone(), 1),
+                ]
+            },
+        )
+    }
+}
+
+impl<T: Value + Sub<Output = T> + 'static> Sub<T> for &Var<T> {
+    type Output = Var<T>;
+
+    fn sub(self, rhs: T) -> Self::Output {
+        Var::from_op(self.value() - rhs, vec![self.clone()], |diff| {
+            vec![diff.unbroadcast(diff.gradient.clone(), 0)]
+        })
+    }
+}
+
+impl<T: Value + Mul<Output = T> + 'static> Mul<&Var<T>> for &Var<T> {
+    type Output = Var<T>;
 
-        self * new_rhs
+    fn mul(self, rhs: &Var<T>) -> Self::Output {
+        Var::from_op(
+            self.value() * rhs.value(),
+            vec![self.clone(), rhs.clone()],
+            |diff| {
+                vec![
+                    diff.unbroadcast(diff.gradient.clone() * diff.children[1].value(), 0),
+                    diff.unbroadcast(diff.gradient.clone() * diff.children[0].value(), 1),
+                ]
+            },
+        )
+    }
+}
+
+impl<T: Value + Mul<Output = T> + 'static> Mul<T> for &Var<T> {
+    type Output = Var<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Var::from_op(
+            self.value() * rhs.clone(),
+            vec![self.clone()],
+            move |diff| vec![diff.unbroadcast(diff.gradient.clone() * rhs.clone(), 0)],
+        )
     }
 }
 
-impl<'a, T: Clone + Zero + MulAssign + Mul<T, Output = T>> Mul<Differentiable<'a, T>>
-    for Differentiable<'a, T>
+impl<T> Div<&Var<T>> for &Var<T>
+where
+    T: Value + Sub<Output = T> + Mul<Output = T> + Div<Output = T> + 'static,
 {
-    type Output = Self;
+    type Output = Var<T>;
 
-    fn mul(self, rhs: Differentiable<'a, T>) -> Self::Output {
-        Differentiable {
-            value: self.value.clone() * rhs.value.clone(),
-            gradient: T::zero(),
-            children: vec![Rc::new(RefCell::new(self)), Rc::new(RefCell::new(rhs))],
-            grad_fn: GradFn(&|diff| {
+    // The backward rule legitimately needs more than division.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: &Var<T>) -> Self::Output {
+        Var::from_op(
+            self.value() / rhs.value(),
+            vec![self.clone(), rhs.clone()],
+            |diff| {
+                // d(a / b)/db = -(a / b) / b, which reuses the output value.
+                let scaled = diff.gradient.clone() / diff.children[1].value();
                 vec![
-                    diff.borrow().value.clone() * diff.borrow().children[1].borrow().value.clone(),
-                    diff.borrow().value.clone() * diff.borrow().children[0].borrow().value.clone(),
+                    diff.unbroadcast(scaled.clone(), 0),
+                    diff.unbroadcast(scaled.zeros_like() - scaled * diff.value.clone(), 1),
                 ]
-            }),
-        }
+            },
+        )
+    }
+}
+
+impl<T: Value + Div<Output = T> + 'static> Div<T> for &Var<T> {
+    type Output = Var<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Var::from_op(
+            self.value() / rhs.clone(),
+            vec![self.clone()],
+            move |diff| vec![diff.unbroadcast(diff.gradient.clone() / rhs.clone(), 0)],
+        )
+    }
+}
+
+impl<T: Value + Neg<Output = T> + 'static> Neg for &Var<T> {
+    type Output = Var<T>;
+
+    fn neg(self) -> Self::Output {
+        Var::from_op(-self.value(), vec![self.clone()], |diff| {
+            vec![-diff.gradient.clone()]
+        })
+    }
+}
+
+impl<T: Value + Neg<Output = T> + 'static> Neg for Var<T> {
+    type Output = Var<T>;
+
+    fn neg(self) -> Self::Output {
+        -&self
     }
 }
 
+/// Implements a binary operator and its assigning variant for owned `Var`
+/// operands in terms of the `&Var op &Var` and `&Var op T` impls.
+///
+/// The assigning operators rebind the handle to the result, leaving the node it
+/// previously pointed to untouched in the graph.
+macro_rules! forward_binop {
+    ($Op:ident, $method:ident, $OpAssign:ident, $method_assign:ident, [$($bounds:tt)+]) => {
+        impl<T: $($bounds)+> $Op<T> for Var<T> {
+            type Output = Var<T>;
+
+            fn $method(self, rhs: T) -> Self::Output {
+                $Op::$method(&self, rhs)
+            }
+        }
+
+        impl<T: $($bounds)+> $Op<&Var<T>> for Var<T> {
+            type Output = Var<T>;
+
+            fn $method(self, rhs: &Var<T>) -> Self::Output {
+                $Op::$method(&self, rhs)
+            }
+        }
+
+        impl<T: $($bounds)+> $Op<Var<T>> for &Var<T> {
+            type Output = Var<T>;
+
+            fn $method(self, rhs: Var<T>) -> Self::Output {
+                $Op::$method(self, &rhs)
+            }
+        }
+
+        impl<T: $($bounds)+> $Op<Var<T>> for Var<T> {
+            type Output = Var<T>;
+
+            fn $method(self, rhs: Var<T>) -> Self::Output {
+                $Op::$method(&self, &rhs)
+            }
+        }
+
+        impl<T: $($bounds)+> $OpAssign<T> for Var<T> {
+            fn $method_assign(&mut self, rhs: T) {
+                *self = $Op::$method(&*self, rhs);
+            }
+        }
+
+        impl<T: $($bounds)+> $OpAssign<&Var<T>> for Var<T> {
+            fn $method_assign(&mut self, rhs: &Var<T>) {
+                *self = $Op::$method(&*self, rhs);
+            }
+        }
+
+        impl<T: $($bounds)+> $OpAssign<Var<T>> for Var<T> {
+            fn $method_assign(&mut self, rhs: Var<T>) {
+                *self = $Op::$method(&*self, &rhs);
+            }
+        }
+    };
+}
+
+forward_binop!(
+    Add,
+    add,
+    AddAssign,
+    add_assign,
+    [Value + Add<Output = T> + 'static]
+);
+forward_binop!(
+    Sub,
+    sub,
+    SubAssign,
+    sub_assign,
+    [Value + Sub<Output = T> + 'static]
+);
+forward_binop!(
+    Mul,
+    mul,
+    MulAssign,
+    mul_assign,
+    [Value + Mul<Output = T> + 'static]
+);
+forward_binop!(
+    Div,
+    div,
+    DivAssign,
+    div_assign,
+    [Value + Sub<Output = T> + Mul<Output = T> + Div<Output = T> + 'static]
+);
+
+/// Implements the arithmetic operators with a constant of a primitive type on
+/// the left-hand side of a `Var` holding either that type (`f64`) or arrays of
+/// it (`f64 => ArrayD<f64>`). These can't be written generically because of
+/// the orphan rules.
+macro_rules! impl_scalar_lhs {
+    ($($t:ty => $value:ty),*) => {$(
+        impl std::ops::Add<&$crate::Var<$value>> for $t {
+            type Output = $crate::Var<$value>;
+
+            fn add(self, rhs: &$crate::Var<$value>) -> Self::Output {
+                rhs + self
+            }
+        }
+
+        impl std::ops::Sub<&$crate::Var<$value>> for $t {
+            type Output = $crate::Var<$value>;
+
+            fn sub(self, rhs: &$crate::Var<$value>) -> Self::Output {
+                $crate::Var::from_op(self - rhs.value(), vec![rhs.clone()], |diff| {
+                    vec![-diff.gradient.clone()]
+                })
+            }
+        }
+
+        impl std::ops::Mul<&$crate::Var<$value>> for $t {
+            type Output = $crate::Var<$value>;
+
+            fn mul(self, rhs: &$crate::Var<$value>) -> Self::Output {
+                rhs * self
+            }
+        }
+
+        impl std::ops::Div<&$crate::Var<$value>> for $t {
+            type Output = $crate::Var<$value>;
+
+            #[allow(clippy::suspicious_arithmetic_impl)]
+            fn div(self, rhs: &$crate::Var<$value>) -> Self::Output {
+                $crate::Var::from_op(self / rhs.value(), vec![rhs.clone()], |diff| {
+                    let x = &diff.children()[0].borrow().value;
+                    vec![-(diff.gradient.clone() / x) * &diff.value]
+                })
+            }
+        }
+
+        impl_scalar_lhs!(@owned $t => $value, Add, add);
+        impl_scalar_lhs!(@owned $t => $value, Sub, sub);
+        impl_scalar_lhs!(@owned $t => $value, Mul, mul);
+        impl_scalar_lhs!(@owned $t => $value, Div, div);
+    )*};
+    (@owned $t:ty => $value:ty, $Op:ident, $method:ident) => {
+        impl std::ops::$Op<$crate::Var<$value>> for $t {
+            type Output = $crate::Var<$value>;
+
+            fn $method(self, rhs: $crate::Var<$value>) -> Self::Output {
+                std::ops::$Op::$method(self, &rhs)
+            }
+        }
+    };
+    ($($t:ty),*) => {
+        impl_scalar_lhs!($($t => $t),*);
+    };
+}
+
+pub(crate) use impl_scalar_lhs;
+
+impl_scalar_lhs!(f32, f64, i8, i16, i32, i64, i128, isize);
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::check_gradients;
 
     #[test]
     fn constants() {
@@ -109,13 +628,247 @@ mod tests {
 
     #[test]
     fn multiplication() {
-        let left = Differentiable::from(2);
-        let right = Differentiable::from(3);
-        let result = left * right;
+        let left = Var::new(2);
+        let right = Var::new(3);
+        let result = &left * &right;
+
+        result.backward();
+
+        assert_eq!(result.value(), 6);
+        assert_eq!(result.gradient(), 1);
+        assert_eq!(left.gradient(), 3);
+        assert_eq!(right.gradient(), 2);
+    }
+
+    #[test]
+    fn reused_operand_accumulates_gradient() {
+        let x = Var::new(3);
+        let result = &x * &x;
+
+        result.backward();
+
+        assert_eq!(x.gradient(), 6);
+    }
+
+    #[test]
+    fn repeated_backward_accumulates_into_leaves_only() {
+        let x = Var::new(3);
+        let y = Var::new(4);
+        let product = &x * &y;
+        let result = &product * 2;
+
+        result.backward();
+        result.backward();
+
+        assert_eq!(result.gradient(), 1);
+        assert_eq!(product.gradient(), 2);
+        assert_eq!(x.gradient(), 16);
+        assert_eq!(y.gradient(), 12);
+    }
+
+    #[test]
+    fn reused_operand_shares_node() {
+        let x = Var::new(3);
+        let y = Var::new(4);
+        let square = &x * &x;
+        let result = &square * &y;
+
+        assert_eq!(result.value(), 36);
+        assert!(square.borrow().children[0].ptr_eq(&x));
+        assert!(square.borrow().children[1].ptr_eq(&x));
+        assert!(!square.borrow().children[0].ptr_eq(&y));
+    }
+
+    #[test]
+    fn shared_intermediate_is_visited_once() {
+        let x = Var::new(3);
+        let square = &x * &x;
+        let result = &square * &square;
+
+        assert_eq!(topology_sort(&result).len(), 3);
+
+        result.backward();
+
+        assert_eq!(square.gradient(), 18);
+        assert_eq!(x.gradient(), 108);
+    }
+
+    #[test]
+    fn deep_chain() {
+        let x = Var::new(1.0);
+        let mut result = x.clone();
+        for _ in 0..1_000_000 {
+            result = &result * 1.0;
+        }
+
+        result.backward();
+
+        assert_eq!(x.gradient(), 1.0);
+    }
+
+    #[test]
+    fn freeing_the_graph_keeps_values_and_gradients() {
+        let x = Var::new(3);
+        let y = Var::new(4);
+        let product = &x * &y;
+        let result = &product * 2;
 
-        backward(result);
+        result.backward_with(false);
 
-        // Print
-        println!("{:?}", result);
+        assert_eq!(x.gradient(), 8);
+        assert_eq!(product.value(), 12);
+        assert_eq!(product.gradient(), 2);
+        assert!(product.borrow().children().is_empty());
+        assert!(!product.is_leaf());
+        assert!(x.is_leaf());
+
+        // The leaves can still be used in new graphs.
+        (&x * 2).backward_with(false);
+        assert_eq!(x.gradient(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "backward through a graph that has already been freed")]
+    fn backward_twice_on_a_freed_graph_panics() {
+        let x = Var::new(3);
+        let result = &x * &x;
+
+        result.backward_with(false);
+        result.backward();
+    }
+
+    #[test]
+    #[should_panic(expected = "backward through a graph that has already been freed")]
+    fn backward_through_a_freed_intermediate_panics() {
+        let x = Var::new(3);
+        let square = &x * &x;
+        (&square * 2).backward_with(false);
+
+        (&square + 1).backward();
+    }
+
+    #[test]
+    fn zero_grad_graph_resets_every_node() {
+        let x = Var::new(3);
+        let y = Var::new(4);
+        let result = &(&x * &y) + &x;
+        result.backward();
+
+        result.zero_grad_graph();
+
+        assert_eq!(x.gradient(), 0);
+        assert_eq!(y.gradient(), 0);
+        assert_eq!(result.gradient(), 0);
+
+        result.backward();
+        assert_eq!(x.gradient(), 5);
+    }
+
+    #[test]
+    fn zero_grad_graph_reaches_leaves_after_freeing() {
+        let x = Var::new(3);
+        let result = &(&x * &x) * 2;
+        result.backward_with(false);
+        assert_eq!(x.gradient(), 12);
+
+        result.zero_grad_graph();
+
+        assert_eq!(x.gradient(), 0);
+        assert_eq!(result.gradient(), 0);
+    }
+
+    struct Model {
+        weight: Var<i32>,
+    }
+
+    impl Model {
+        fn forward(&self, input: i32) -> Var<i32> {
+            &self.weight * input
+        }
+    }
+
+    #[test]
+    fn graphs_outlive_their_builder() {
+        let model = Model {
+            weight: Var::new(5),
+        };
+        let output = model.forward(3);
+
+        assert_eq!(output.value(), 15);
+        assert!(output.borrow().children[0].ptr_eq(&model.weight));
+    }
+
+    #[test]
+    fn finite_differences_mul() {
+        check_gradients(&[1.5, -2.0], |x| &x[0] * &x[1]);
+        check_gradients(&[0.7], |x| &x[0] * &x[0]);
+    }
+
+    #[test]
+    fn finite_differences_add() {
+        check_gradients(&[1.5, -2.0], |x| &x[0] + &x[1]);
+        check_gradients(&[1.5], |x| &x[0] + 3.0);
+        check_gradients(&[1.5], |x| 3.0 + &x[0]);
+    }
+
+    #[test]
+    fn finite_differences_sub() {
+        check_gradients(&[1.5, -2.0], |x| &x[0] - &x[1]);
+        check_gradients(&[1.5], |x| &x[0] - 3.0);
+        check_gradients(&[1.5], |x| 3.0 - &x[0]);
+    }
+
+    #[test]
+    fn finite_differences_div() {
+        check_gradients(&[1.5, -2.0], |x| &x[0] / &x[1]);
+        check_gradients(&[1.5], |x| &x[0] / 3.0);
+        check_gradients(&[1.5], |x| 3.0 / &x[0]);
+    }
+
+    #[test]
+    fn finite_differences_neg() {
+        check_gradients(&[1.5], |x| -&x[0]);
+        check_gradients(&[1.5, 0.5], |x| -(&x[0] * &x[1]));
+    }
+
+    #[test]
+    fn finite_differences_assign() {
+        check_gradients(&[1.5, -2.0], |x| {
+            let mut result = x[0].clone();
+            result += &x[1];
+            result *= &x[0];
+            result -= 0.5;
+            result /= &x[1];
+            result
+        });
+    }
+
+    #[test]
+    fn finite_differences_formula() {
+        check_gradients(&[1.5, -2.0], |x| 3.0 * &x[0] - &x[1] / 2.0);
+        check_gradients(&[0.3, 1.2], |x| {
+            (&x[0] * &x[1] + &x[0]) / (1.0 - &x[1]) - 2.0 * &x[0]
+        });
+    }
+
+    #[test]
+    fn assign_keeps_original_node() {
+        let x = Var::new(2);
+        let mut y = x.clone();
+        y *= 3;
+        y += &x;
+
+        assert_eq!(x.value(), 2);
+        assert_eq!(y.value(), 8);
+
+        y.backward();
+
+        assert_eq!(x.gradient(), 4);
+    }
+
+    #[test]
+    fn finite_differences_mul_constant() {
+        check_gradients(&[1.5], |x| &x[0] * 3.0);
+        check_gradients(&[-0.5, 2.5], |x| (&x[0] * -4.0) * &x[1]);
     }
 }
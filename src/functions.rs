@@ -0,0 +1,197 @@
+//! Elementary transcendental functions on [`Var`].
+
+use num::{traits::FloatConst, Float, NumCast, One, Zero};
+
+use crate::{Elementwise, Var};
+
+impl<T: Elementwise + 'static> Var<T> {
+    /// Applies `f` to every element, with `derivative` computing the derivative
+    /// of `f` from an input element and the matching output element.
+    pub(crate) fn unary(
+        &self,
+        f: impl Fn(T::Elem) -> T::Elem,
+        derivative: impl Fn(T::Elem, T::Elem) -> T::Elem + 'static,
+    ) -> Self {
+        Var::from_op(self.value().map(f), vec![self.clone()], move |diff| {
+            let input = &diff.children()[0].borrow().value;
+            vec![diff.gradient.clone() * input.zip_map(&diff.value, &derivative)]
+        })
+    }
+
+    /// Computes `e^self`.
+    pub fn exp(&self) -> Self {
+        self.unary(T::Elem::exp, |_, y| y)
+    }
+
+    /// Computes the natural logarithm.
+    pub fn ln(&self) -> Self {
+        self.unary(T::Elem::ln, |x, _| x.recip())
+    }
+
+    /// Computes the base 2 logarithm.
+    pub fn log2(&self) -> Self
+    where
+        T::Elem: FloatConst,
+    {
+        self.unary(T::Elem::log2, |x, _| (x * T::Elem::LN_2()).recip())
+    }
+
+    /// Computes the base 10 logarithm.
+    pub fn log10(&self) -> Self
+    where
+        T::Elem: FloatConst,
+    {
+        self.unary(T::Elem::log10, |x, _| (x * T::Elem::LN_10()).recip())
+    }
+
+    /// Computes the square root.
+    pub fn sqrt(&self) -> Self {
+        self.unary(T::Elem::sqrt, |_, y| (y + y).recip())
+    }
+
+    /// Raises the value to a constant floating point power.
+    pub fn powf(&self, n: T::Elem) -> Self {
+        self.unary(|x| x.powf(n), move |x, _| n * x.powf(n - T::Elem::one()))
+    }
+
+    /// Raises the value to a constant integer power.
+    pub fn powi(&self, n: i32) -> Self {
+        self.unary(
+            |x| x.powi(n),
+            move |x, _| <T::Elem as NumCast>::from(n).unwrap() * x.powi(n - 1),
+        )
+    }
+
+    /// Computes the absolute value. The derivative at zero is taken to be zero.
+    pub fn abs(&self) -> Self {
+        self.unary(T::Elem::abs, |x, _| {
+            if x == T::Elem::zero() {
+                x
+            } else {
+                x.signum()
+            }
+        })
+    }
+
+    /// Computes the sine, in radians.
+    pub fn sin(&self) -> Self {
+        self.unary(T::Elem::sin, |x, _| x.cos())
+    }
+
+    /// Computes the cosine, in radians.
+    pub fn cos(&self) -> Self {
+        self.unary(T::Elem::cos, |x, _| -x.sin())
+    }
+
+    /// Computes the tangent, in radians.
+    pub fn tan(&self) -> Self {
+        self.unary(T::Elem::tan, |_, y| T::Elem::one() + y * y)
+    }
+
+    /// Computes the arcsine, in radians.
+    pub fn asin(&self) -> Self {
+        self.unary(T::Elem::asin, |x, _| {
+            (T::Elem::one() - x * x).sqrt().recip()
+        })
+    }
+
+    /// Computes the arccosine, in radians.
+    pub fn acos(&self) -> Self {
+        self.unary(T::Elem::acos, |x, _| {
+            -(T::Elem::one() - x * x).sqrt().recip()
+        })
+    }
+
+    /// Computes the arctangent, in radians.
+    pub fn atan(&self) -> Self {
+        self.unary(T::Elem::atan, |x, _| (T::Elem::one() + x * x).recip())
+    }
+
+    /// Computes the four quadrant arctangent of `self` (y) and `other` (x), in
+    /// radians.
+    pub fn atan2(&self, other: &Var<T>) -> Self {
+        Var::from_op(
+            self.value().zip_map(&other.value(), Float::atan2),
+            vec![self.clone(), other.clone()],
+            |diff| {
+                let y = &diff.children()[0].borrow().value;
+                let x = &diff.children()[1].borrow().value;
+                vec![
+                    diff.gradient.clone() * y.zip_map(x, |y, x| x / (x * x + y * y)),
+                    diff.gradient.clone() * y.zip_map(x, |y, x| -y / (x * x + y * y)),
+                ]
+            },
+        )
+    }
+
+    /// Computes the hyperbolic sine.
+    pub fn sinh(&self) -> Self {
+        self.unary(T::Elem::sinh, |x, _| x.cosh())
+    }
+
+    /// Computes the hyperbolic cosine.
+    pub fn cosh(&self) -> Self {
+        self.unary(T::Elem::cosh, |x, _| x.sinh())
+    }
+
+    /// Computes the hyperbolic tangent.
+    pub fn tanh(&self) -> Self {
+        self.unary(T::Elem::tanh, |_, y| T::Elem::one() - y * y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::check_gradients;
+
+    #[test]
+    fn finite_differences_exp_and_logarithms() {
+        check_gradients(&[0.7], |x| x[0].exp());
+        check_gradients(&[0.7], |x| x[0].ln());
+        check_gradients(&[0.7], |x| x[0].log2());
+        check_gradients(&[0.7], |x| x[0].log10());
+    }
+
+    #[test]
+    fn finite_differences_powers() {
+        check_gradients(&[0.7], |x| x[0].sqrt());
+        check_gradients(&[0.7], |x| x[0].powf(2.5));
+        check_gradients(&[-0.7], |x| x[0].powi(3));
+        check_gradients(&[0.7], |x| x[0].powi(-2));
+        check_gradients(&[-0.7], |x| x[0].abs());
+        check_gradients(&[0.7], |x| x[0].abs());
+    }
+
+    #[test]
+    fn finite_differences_trigonometric() {
+        check_gradients(&[0.7], |x| x[0].sin());
+        check_gradients(&[0.7], |x| x[0].cos());
+        check_gradients(&[0.7], |x| x[0].tan());
+        check_gradients(&[0.3], |x| x[0].asin());
+        check_gradients(&[0.3], |x| x[0].acos());
+        check_gradients(&[0.3], |x| x[0].atan());
+        check_gradients(&[0.3, -1.2], |x| x[0].atan2(&x[1]));
+    }
+
+    #[test]
+    fn finite_differences_hyperbolic() {
+        check_gradients(&[0.7], |x| x[0].sinh());
+        check_gradients(&[0.7], |x| x[0].cosh());
+        check_gradients(&[0.7], |x| x[0].tanh());
+    }
+
+    #[test]
+    fn finite_differences_composition() {
+        check_gradients(&[0.4, 1.3], |x| {
+            (x[0].sin() * x[1].exp()).ln() + x[1].sqrt()
+        });
+    }
+
+    #[test]
+    fn values() {
+        let x = crate::Var::new(4.0_f64);
+        assert_eq!(x.sqrt().value(), 2.0);
+        assert_eq!(x.powi(2).value(), 16.0);
+        assert_eq!(x.log2().value(), 2.0);
+    }
+}
@@ -0,0 +1,119 @@
+//! Switching the recording of the computation graph on and off.
+//!
+//! While recording is off, operations still compute their values but return
+//! leaves with no backward rule, so inference doesn't pay for a graph it will
+//! never differentiate.
+
+use std::{cell::Cell, marker::PhantomData};
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Restores the previous recording mode of the thread when dropped.
+///
+/// Guards must be dropped in the reverse order they were created: dropping an
+/// outer [`no_grad`] guard before an inner [`enable_grad`] one leaves
+/// recording off once both are gone. Scoping each guard to a block keeps the
+/// order right. A guard can't be sent to another thread, as the mode it
+/// restores belongs to the thread that created it.
+#[must_use = "the mode is restored as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct GradGuard {
+    previous: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for GradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.previous));
+    }
+}
+
+fn set_grad_enabled(enabled: bool) -> GradGuard {
+    GradGuard {
+        previous: GRAD_ENABLED.with(|cell| cell.replace(enabled)),
+        _not_send: PhantomData,
+    }
+}
+
+/// Stops recording operations on this thread until the returned guard is
+/// dropped.
+///
+/// ```
+/// # use mkgrad::{no_grad, Var};
+/// let x = Var::new(2.0);
+/// let y = {
+///     let _guard = no_grad();
+///     &x * &x
+/// };
+/// assert!(y.is_leaf());
+/// ```
+pub fn no_grad() -> GradGuard {
+    set_grad_enabled(false)
+}
+
+/// Records operations on this thread until the returned guard is dropped, even
+/// inside [`no_grad`].
+pub fn enable_grad() -> GradGuard {
+    set_grad_enabled(true)
+}
+
+/// Returns whether operations on this thread are currently recorded.
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(Cell::get)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::{Tensor, Var};
+
+    #[test]
+    fn no_grad_skips_the_graph() {
+        let x = Var::new(3.0);
+        let y = {
+            let _guard = no_grad();
+            assert!(!is_grad_enabled());
+            (&x * &x).sin()
+        };
+
+        assert!(is_grad_enabled());
+        assert_eq!(y.value(), 9.0_f64.sin());
+        assert!(y.is_leaf());
+
+        y.backward();
+        assert_eq!(x.gradient(), 0.0);
+    }
+
+    #[test]
+    fn guards_nest() {
+        let x = Var::new(3.0);
+        let _outer = no_grad();
+        {
+            let _inner = enable_grad();
+            let y = &x * 2.0;
+            assert!(!y.is_leaf());
+        }
+        assert!(!is_grad_enabled());
+        assert!((&x * 2.0).is_leaf());
+    }
+
+    #[test]
+    fn detach_cuts_the_graph() {
+        let x = Tensor::new(array![1.0, 2.0].into_dyn());
+        let square = &x * &x;
+        let target = square.detach();
+        let loss = (&square - &target).sum(None, false) + &(&square * &target).sum(None, false);
+
+        loss.backward();
+
+        assert!(target.is_leaf());
+        assert!(!target.ptr_eq(&square));
+        assert_eq!(target.value(), square.value());
+        // Only the path through `square` contributes: 2x (1 + x²).
+        assert_eq!(x.gradient(), array![4.0, 20.0].into_dyn());
+    }
+}
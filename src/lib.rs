@@ -1,4 +1,19 @@
+mod activations;
 mod differentiable;
+mod functions;
+mod grad_mode;
+pub mod losses;
+pub mod nn;
+pub mod optim;
+mod tensor;
+#[cfg(test)]
+mod testing;
+mod value;
+
+pub use differentiable::{Differentiable, Var};
+pub use grad_mode::{enable_grad, is_grad_enabled, no_grad, GradGuard};
+pub use tensor::{Conv1dOptions, Conv2dOptions, Element, Tensor};
+pub use value::{Elementwise, Value};
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
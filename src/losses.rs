@@ -0,0 +1,352 @@
+//! Loss functions comparing predictions with targets.
+//!
+//! Every loss computes one value per example and then reduces them as asked
+//! by its [`Reduction`]. Class targets are given as integer ids, everything
+//! else as tensors of the same shape as the predictions.
+
+use crate::{value::constant, Element, Tensor};
+
+/// How the per-example losses are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Reduction {
+    /// Keep the loss of every example.
+    None,
+    /// Average the losses.
+    #[default]
+    Mean,
+    /// Add the losses up.
+    Sum,
+}
+
+impl Reduction {
+    /// Reduces a tensor of losses.
+    pub fn apply<A: Element>(self, losses: &Tensor<A>) -> Tensor<A> {
+        match self {
+            Reduction::None => losses.clone(),
+            Reduction::Mean => losses.mean(None, false),
+            Reduction::Sum => losses.sum(None, false),
+        }
+    }
+}
+
+/// Returns `[batch, 1]` ids for picking one class of each row of a
+/// `[batch, classes]` tensor with `gather`.
+fn class_ids<A: Element>(scores: &Tensor<A>, targets: &[usize]) -> ndarray::ArrayD<usize> {
+    let shape = scores.borrow().value.shape().to_vec();
+    assert!(
+        shape.len() == 2 && shape[0] == targets.len(),
+        "expected [batch, classes] scores for {} targets, got shape {shape:?}",
+        targets.len()
+    );
+    ndarray::Array2::from_shape_vec((targets.len(), 1), targets.to_vec())
+        .unwrap()
+        .into_dyn()
+}
+
+/// The squared error `(input - target)²`.
+pub fn mse<A: Element>(input: &Tensor<A>, target: &Tensor<A>, reduction: Reduction) -> Tensor<A> {
+    let difference = input - target;
+    reduction.apply(&(&difference * &difference))
+}
+
+/// The absolute error `|input - target|`.
+pub fn l1<A: Element>(input: &Tensor<A>, target: &Tensor<A>, reduction: Reduction) -> Tensor<A> {
+    reduction.apply(&(input - target).abs())
+}
+
+/// The Huber loss: squared error `d² / 2` for differences `d` up to `delta`,
+/// and `delta (|d| - delta / 2)` beyond it.
+pub fn huber<A: Element>(
+    input: &Tensor<A>,
+    target: &Tensor<A>,
+    delta: A,
+    reduction: Reduction,
+) -> Tensor<A> {
+    let half = constant::<A>(0.5);
+    let losses = (input - target).unary(
+        move |d| {
+            if d.abs() <= delta {
+                half * d * d
+            } else {
+                delta * (d.abs() - half * delta)
+            }
+        },
+        move |d, _| d.max(-delta).min(delta),
+    );
+    reduction.apply(&losses)
+}
+
+/// The smooth L1 loss, the [`huber`] loss divided by `beta`: quadratic for
+/// differences up to `beta` and the absolute error minus `beta / 2` beyond it.
+pub fn smooth_l1<A: Element>(
+    input: &Tensor<A>,
+    target: &Tensor<A>,
+    beta: A,
+    reduction: Reduction,
+) -> Tensor<A> {
+    &huber(input, target, beta, reduction) / beta
+}
+
+/// The cross entropy between predicted probabilities and target
+/// probabilities. Logarithms are clamped at -100 so that probabilities of
+/// exactly 0 or 1 give finite losses.
+pub fn binary_cross_entropy<A: Element>(
+    input: &Tensor<A>,
+    target: &Tensor<A>,
+    reduction: Reduction,
+) -> Tensor<A> {
+    let floor = constant::<A>(-100.0);
+    let clamped_ln = |x: &Tensor<A>| {
+        x.unary(
+            move |p| p.ln().max(floor),
+            move |p, y| if y > floor { p.recip() } else { A::zero() },
+        )
+    };
+    let positive = target * &clamped_ln(input);
+    let complement = |x: &Tensor<A>| -x + A::one();
+    let negative = &complement(target) * &clamped_ln(&complement(input));
+    reduction.apply(&-(&positive + &negative))
+}
+
+/// The binary cross entropy of `sigmoid(input)`, computed from the logits as
+/// `softplus(x) - t x` so that it never overflows.
+pub fn binary_cross_entropy_with_logits<A: Element>(
+    input: &Tensor<A>,
+    target: &Tensor<A>,
+    reduction: Reduction,
+) -> Tensor<A> {
+    reduction.apply(&(&input.softplus() - &(target * input)))
+}
+
+/// The negative log likelihood of `target` classes under `[batch, classes]`
+/// log probabilities.
+pub fn nll<A: Element>(input: &Tensor<A>, targets: &[usize], reduction: Reduction) -> Tensor<A> {
+    let picked = input.gather(1, &class_ids(input, targets)).squeeze(1);
+    reduction.apply(&-picked)
+}
+
+/// The cross entropy of `target` classes under the softmax of
+/// `[batch, classes]` logits.
+///
+/// With `label_smoothing` ε, the target distribution puts `1 - ε` on the
+/// target class and spreads ε evenly over all classes.
+pub fn cross_entropy<A: Element>(
+    input: &Tensor<A>,
+    targets: &[usize],
+    label_smoothing: A,
+    reduction: Reduction,
+) -> Tensor<A> {
+    let log_probabilities = input.log_softmax(1);
+    let losses = nll(&log_probabilities, targets, Reduction::None);
+    if label_smoothing == A::zero() {
+        return reduction.apply(&losses);
+    }
+
+    let smoothed = -log_probabilities.mean(Some(1), false);
+    let losses = &(&losses * (A::one() - label_smoothing)) + &(&smoothed * label_smoothing);
+    reduction.apply(&losses)
+}
+
+/// The Kullback-Leibler divergence `t (ln t - x)` of target probabilities
+/// `t` from predicted log probabilities `x`. Terms with `t = 0` are zero.
+pub fn kl_div<A: Element>(
+    input: &Tensor<A>,
+    target: &Tensor<A>,
+    reduction: Reduction,
+) -> Tensor<A> {
+    let entropy = target.unary(
+        |t| if t > A::zero() { t * t.ln() } else { A::zero() },
+        |t, _| {
+            if t > A::zero() {
+                t.ln() + A::one()
+            } else {
+                A::zero()
+            }
+        },
+    );
+    reduction.apply(&(&entropy - &(target * input)))
+}
+
+/// The hinge loss `max(0, margin - t x)` for targets of -1 or 1.
+pub fn hinge<A: Element>(
+    input: &Tensor<A>,
+    target: &Tensor<A>,
+    margin: A,
+    reduction: Reduction,
+) -> Tensor<A> {
+    reduction.apply(&(-(target * input) + margin).relu())
+}
+
+/// A loss on the cosine similarity of the rows of two `[batch, features]`
+/// tensors: `1 - cos` for pairs with a target of 1 and `max(0, cos - margin)`
+/// for pairs with a target of -1. The target has shape `[batch]`.
+pub fn cosine_embedding<A: Element>(
+    first: &Tensor<A>,
+    second: &Tensor<A>,
+    target: &Tensor<A>,
+    margin: A,
+    reduction: Reduction,
+) -> Tensor<A> {
+    let eps = constant::<A>(1e-12);
+    let squared_norm = |x: &Tensor<A>| (x * x).sum(Some(1), false);
+    let cosine = &(first * second).sum(Some(1), false)
+        / &(&(&squared_norm(first) * &squared_norm(second)) + eps).sqrt();
+
+    let positive = target
+        .borrow()
+        .value
+        .mapv(|t| A::from((t > A::zero()) as u8).unwrap());
+    let negative = positive.mapv(|p| A::one() - p);
+    let losses = &((-&cosine + A::one()) * positive) + &((&cosine - margin).relu() * negative);
+    reduction.apply(&losses)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, ArrayD};
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence};
+
+    const REDUCTIONS: [Reduction; 3] = [Reduction::None, Reduction::Mean, Reduction::Sum];
+
+    fn predictions() -> ArrayD<f64> {
+        array![[0.5, -1.5, 2.0], [1.0, 0.25, -0.75]].into_dyn()
+    }
+
+    fn targets() -> ArrayD<f64> {
+        array![[1.5, 0.5, 1.0], [0.75, -0.25, 2.0]].into_dyn()
+    }
+
+    fn probabilities() -> ArrayD<f64> {
+        array![[0.2, 0.7, 0.1], [0.6, 0.3, 0.1]].into_dyn()
+    }
+
+    fn loss_value(loss: Tensor<f64>) -> f64 {
+        loss.value()[[]]
+    }
+
+    #[test]
+    fn reductions() {
+        let (x, y) = (Tensor::new(predictions()), Tensor::new(targets()));
+        let none = mse(&x, &y, Reduction::None).value();
+        assert_eq!(none, (predictions() - targets()).mapv(|d| d * d));
+        assert_eq!(loss_value(mse(&x, &y, Reduction::Sum)), none.sum());
+        assert!((loss_value(mse(&x, &y, Reduction::Mean)) - none.mean().unwrap()).abs() < 1e-12);
+        assert_eq!(Reduction::default(), Reduction::Mean);
+    }
+
+    #[test]
+    fn regression_values() {
+        let x = Tensor::new(array![0.0, 0.5, 3.0].into_dyn());
+        let y = Tensor::new(ArrayD::zeros(vec![3]));
+        assert_eq!(
+            l1(&x, &y, Reduction::None).value(),
+            array![0.0, 0.5, 3.0].into_dyn()
+        );
+        assert_eq!(
+            huber(&x, &y, 1.0, Reduction::None).value(),
+            array![0.0, 0.125, 2.5].into_dyn()
+        );
+        assert_eq!(
+            smooth_l1(&x, &y, 2.0, Reduction::None).value(),
+            array![0.0, 0.0625, 2.0].into_dyn()
+        );
+    }
+
+    #[test]
+    fn classification_values() {
+        let logits = Tensor::new(array![[2.0, 0.0, -1.0], [0.0, 0.0, 0.0]].into_dyn());
+        let losses = cross_entropy(&logits, &[0, 2], 0.0, Reduction::None).value();
+        let expected = (1.0 + (-2.0_f64).exp() + (-3.0_f64).exp()).ln();
+        assert!((losses[0] - expected).abs() < 1e-12);
+        assert!((losses[1] - 3.0_f64.ln()).abs() < 1e-12);
+
+        // Smoothing everything away makes the target irrelevant.
+        let uniform = cross_entropy(&logits, &[0, 2], 1.0, Reduction::None).value();
+        let other = cross_entropy(&logits, &[1, 0], 1.0, Reduction::None).value();
+        assert!((uniform - other).iter().all(|d| d.abs() < 1e-12));
+
+        // Huge logits don't overflow.
+        let large = Tensor::new(array![[1000.0, -1000.0]].into_dyn());
+        assert_eq!(
+            loss_value(cross_entropy(&large, &[0], 0.0, Reduction::Mean)),
+            0.0
+        );
+        let with_logits = binary_cross_entropy_with_logits(
+            &large,
+            &Tensor::new(array![[0.0, 1.0]].into_dyn()),
+            Reduction::Sum,
+        );
+        assert_eq!(loss_value(with_logits), 2000.0);
+
+        let certain = Tensor::new(array![0.0, 1.0].into_dyn());
+        let wrong = binary_cross_entropy(
+            &certain,
+            &Tensor::new(array![1.0, 0.0].into_dyn()),
+            Reduction::Sum,
+        );
+        assert_eq!(loss_value(wrong), 200.0);
+    }
+
+    #[test]
+    fn divergence_and_margins() {
+        let p = Tensor::new(probabilities());
+        assert!(loss_value(kl_div(&p.ln(), &p, Reduction::Sum)).abs() < 1e-12);
+
+        let x = Tensor::new(array![2.0, 0.5, -0.5].into_dyn());
+        let t = Tensor::new(array![1.0, 1.0, 1.0].into_dyn());
+        assert_eq!(
+            hinge(&x, &t, 1.0, Reduction::None).value(),
+            array![0.0, 0.5, 1.5].into_dyn()
+        );
+
+        let a = Tensor::new(array![[1.0, 0.0], [1.0, 0.0]].into_dyn());
+        let b = Tensor::new(array![[2.0, 0.0], [1.0, 1.0]].into_dyn());
+        let t = Tensor::new(array![1.0, -1.0].into_dyn());
+        let losses: ArrayD<f64> = cosine_embedding(&a, &b, &t, 0.5, Reduction::None).value();
+        assert!(losses[0].abs() < 1e-12);
+        assert!((losses[1] - (0.5_f64.sqrt() - 0.5)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn finite_differences_regression_losses() {
+        let inputs = [predictions(), targets()];
+        for reduction in REDUCTIONS {
+            check_tensor_gradients(&inputs, |x| mse(&x[0], &x[1], reduction));
+            check_tensor_gradients(&inputs, |x| l1(&x[0], &x[1], reduction));
+            check_tensor_gradients(&inputs, |x| huber(&x[0], &x[1], 0.8, reduction));
+            check_tensor_gradients(&inputs, |x| smooth_l1(&x[0], &x[1], 1.2, reduction));
+            check_tensor_gradients(&inputs, |x| hinge(&x[0], &x[1], 1.0, reduction));
+        }
+    }
+
+    #[test]
+    fn finite_differences_classification_losses() {
+        let inputs = [predictions(), probabilities()];
+        for reduction in REDUCTIONS {
+            check_tensor_gradients(&inputs, |x| {
+                binary_cross_entropy(&x[0].sigmoid(), &x[1], reduction)
+            });
+            check_tensor_gradients(&inputs, |x| {
+                binary_cross_entropy_with_logits(&x[0], &x[1], reduction)
+            });
+            check_tensor_gradients(&inputs, |x| kl_div(&x[0], &x[1], reduction));
+            check_tensor_gradients(&inputs[..1], |x| {
+                cross_entropy(&x[0], &[2, 0], 0.1, reduction)
+            });
+            check_tensor_gradients(&inputs[..1], |x| nll(&x[0], &[1, 1], reduction));
+        }
+    }
+
+    #[test]
+    fn finite_differences_cosine_embedding() {
+        let inputs = [sequence(&[3, 4]), sequence(&[3, 4]).mapv(f64::cos)];
+        let target = Tensor::new(array![1.0, -1.0, -1.0].into_dyn());
+        for reduction in REDUCTIONS {
+            check_tensor_gradients(&inputs, |x| {
+                cosine_embedding(&x[0], &x[1], &target, -0.5, reduction)
+            });
+        }
+    }
+}
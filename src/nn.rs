@@ -0,0 +1,188 @@
+//! Building blocks for neural networks.
+//!
+//! Models implement [`Module`], which exposes their trainable parameters so
+//! that they can be reset and updated without knowing the model's layout.
+
+mod attention;
+mod conv;
+mod layers;
+mod recurrent;
+mod rng;
+
+pub use attention::{scaled_dot_product_attention, MultiHeadAttention, TransformerBlock};
+pub use conv::{AdaptiveAvgPool2d, AvgPool2d, Conv1d, Conv2d, ConvTranspose2d, MaxPool2d};
+pub use layers::{Activation, BatchNorm1d, Dropout, Embedding, LayerNorm, Linear, Mlp, Sequential};
+pub use recurrent::{unroll, unroll_sequence, Cell, GruCell, LstmCell, RnnCell};
+pub use rng::Rng;
+
+use crate::{Element, Tensor};
+
+/// A part of a model, mapping an input tensor to an output tensor.
+///
+/// A module's parameters are leaves of the graph that it owns and that an
+/// optimiser should update. Everything else computed during `forward` is an
+/// intermediate value.
+pub trait Module<A: Element> {
+    /// Computes the output of the module for `input`.
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A>;
+
+    /// Returns the parameters of the module and its submodules, named by
+    /// their path, such as `layers.0.weight`.
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)>;
+
+    /// Returns the parameters of the module and its submodules.
+    fn parameters(&self) -> Vec<Tensor<A>> {
+        self.named_parameters()
+            .into_iter()
+            .map(|(_, parameter)| parameter)
+            .collect()
+    }
+
+    /// Resets the gradients of all parameters to zero.
+    fn zero_grad(&self) {
+        for parameter in self.parameters() {
+            parameter.zero_grad();
+        }
+    }
+
+    /// Switches the module and its submodules between training and evaluation
+    /// behaviour. Modules that behave the same in both modes can ignore it.
+    fn set_training(&mut self, _training: bool) {}
+
+    /// Switches the module to training behaviour.
+    fn train(&mut self) {
+        self.set_training(true);
+    }
+
+    /// Switches the module to evaluation behaviour.
+    fn eval(&mut self) {
+        self.set_training(false);
+    }
+}
+
+/// Prefixes the names of the parameters of a submodule with its name.
+pub fn prefix_parameters<A: Element>(
+    prefix: &str,
+    parameters: Vec<(String, Tensor<A>)>,
+) -> Vec<(String, Tensor<A>)> {
+    parameters
+        .into_iter()
+        .map(|(name, parameter)| (format!("{prefix}.{name}"), parameter))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, ArrayD};
+
+    use super::*;
+
+    struct Affine {
+        scale: Tensor<f64>,
+        shift: Tensor<f64>,
+    }
+
+    impl Affine {
+        fn new() -> Self {
+            Affine {
+                scale: Tensor::new(array![2.0, 3.0].into_dyn()),
+                shift: Tensor::new(array![0.5, -0.5].into_dyn()),
+            }
+        }
+    }
+
+    impl Module<f64> for Affine {
+        fn forward(&self, input: &Tensor<f64>) -> Tensor<f64> {
+            &(input * &self.scale) + &self.shift
+        }
+
+        fn named_parameters(&self) -> Vec<(String, Tensor<f64>)> {
+            vec![
+                ("scale".to_string(), self.scale.clone()),
+                ("shift".to_string(), self.shift.clone()),
+            ]
+        }
+    }
+
+    struct Stack {
+        first: Affine,
+        second: Affine,
+        training: bool,
+    }
+
+    impl Module<f64> for Stack {
+        fn forward(&self, input: &Tensor<f64>) -> Tensor<f64> {
+            self.second.forward(&self.first.forward(input))
+        }
+
+        fn named_parameters(&self) -> Vec<(String, Tensor<f64>)> {
+            let mut parameters = prefix_parameters("first", self.first.named_parameters());
+            parameters.extend(prefix_parameters("second", self.second.named_parameters()));
+            parameters
+        }
+
+        fn set_training(&mut self, training: bool) {
+            self.training = training;
+        }
+    }
+
+    fn stack() -> Stack {
+        Stack {
+            first: Affine::new(),
+            second: Affine::new(),
+            training: true,
+        }
+    }
+
+    #[test]
+    fn named_parameters_are_nested() {
+        let names: Vec<_> = stack()
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
+        assert_eq!(
+            names,
+            vec!["first.scale", "first.shift", "second.scale", "second.shift"]
+        );
+    }
+
+    #[test]
+    fn parameters_are_the_leaves_the_output_depends_on() {
+        let model = stack();
+        let input = Tensor::new(array![1.0, 1.0].into_dyn());
+        let output = model.forward(&input).sum(None, false);
+
+        output.backward();
+
+        for parameter in model.parameters() {
+            assert!(parameter.is_leaf());
+            assert!(parameter.gradient().iter().all(|&g| g != 0.0));
+        }
+        assert_eq!(model.first.scale.gradient(), array![2.0, 3.0].into_dyn());
+    }
+
+    #[test]
+    fn zero_grad_resets_every_parameter() {
+        let model = stack();
+        let input = Tensor::new(array![1.0, 1.0].into_dyn());
+        model.forward(&input).backward();
+
+        model.zero_grad();
+
+        for parameter in model.parameters() {
+            assert_eq!(parameter.gradient(), ArrayD::zeros(vec![2]));
+        }
+        assert_ne!(input.gradient(), ArrayD::zeros(vec![2]));
+    }
+
+    #[test]
+    fn train_and_eval_switch_modes() {
+        let mut model = stack();
+        model.eval();
+        assert!(!model.training);
+        model.train();
+        assert!(model.training);
+    }
+}
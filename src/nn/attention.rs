@@ -0,0 +1,421 @@
+//! Attention and Transformer blocks over `[batch, length, features]` inputs.
+
+use ndarray::{ArrayD, IxDyn};
+
+use super::{prefix_parameters, Activation, LayerNorm, Linear, Mlp, Module, Rng};
+use crate::{value::constant, Element, Tensor};
+
+/// Computes `softmax(q kᵀ / sqrt(d)) v` for queries of shape `[..., queries,
+/// d]`, keys of shape `[..., keys, d]` and values of shape `[..., keys, dv]`,
+/// giving `[..., queries, dv]`.
+///
+/// `mask` is broadcast to `[..., queries, keys]` and marks the pairs that may
+/// attend to each other, which is how padding is excluded. If `causal` is set,
+/// queries also can't attend to later keys. A query that can't attend to any
+/// key gets zero outputs, and passes no gradient to the keys and values.
+pub fn scaled_dot_product_attention<A: Element>(
+    query: &Tensor<A>,
+    key: &Tensor<A>,
+    value: &Tensor<A>,
+    mask: Option<&ArrayD<bool>>,
+    causal: bool,
+) -> Tensor<A> {
+    let query_shape = query.borrow().value.shape().to_vec();
+    let key_shape = key.borrow().value.shape().to_vec();
+    let value_shape = value.borrow().value.shape().to_vec();
+    let rank = query_shape.len();
+    assert!(
+        rank >= 2,
+        "attention expects [..., length, features] tensors"
+    );
+    let leading = &query_shape[..rank - 2];
+    let (queries, depth, keys, value_depth) = (
+        query_shape[rank - 2],
+        query_shape[rank - 1],
+        key_shape[rank - 2],
+        value_shape[rank - 1],
+    );
+    let batch = leading.iter().product::<usize>();
+
+    let query = query.reshape(&[batch, queries, depth]);
+    let key = key.reshape(&[batch, keys, depth]);
+    let value = value.reshape(&[batch, keys, value_depth]);
+
+    let scale = A::one() / A::from(depth).unwrap().sqrt();
+    let mut scores = &query.bmm(&key.transpose(1, 2)) * scale;
+
+    let mut scores_shape = leading.to_vec();
+    scores_shape.extend([queries, keys]);
+    let mut blocked = ArrayD::from_elem(IxDyn(&scores_shape), false);
+    if let Some(mask) = mask {
+        let mask = mask.broadcast(blocked.raw_dim()).unwrap_or_else(|| {
+            panic!(
+                "attention mask of shape {:?} doesn't broadcast to {scores_shape:?}",
+                mask.shape()
+            )
+        });
+        blocked.zip_mut_with(&mask, |blocked, &allowed| *blocked |= !allowed);
+    }
+    if causal {
+        for (index, blocked) in blocked.indexed_iter_mut() {
+            *blocked |= index[rank - 1] > index[rank - 2];
+        }
+    }
+    if mask.is_some() || causal {
+        let blocked = blocked.into_shape(vec![batch, queries, keys]).unwrap();
+        scores = scores.masked_fill(&blocked, A::neg_infinity());
+    }
+
+    let weights = scores.softmax(2);
+    let mut output_shape = leading.to_vec();
+    output_shape.extend([queries, value_depth]);
+    weights.bmm(&value).reshape(&output_shape)
+}
+
+/// Attention with several heads, each attending over its own slice of the
+/// projected features.
+pub struct MultiHeadAttention<A: Element> {
+    pub query: Linear<A>,
+    pub key: Linear<A>,
+    pub value: Linear<A>,
+    pub output: Linear<A>,
+    pub heads: usize,
+    /// Whether positions can only attend to themselves and earlier positions.
+    pub causal: bool,
+}
+
+impl<A: Element> MultiHeadAttention<A> {
+    pub fn new(embed_dim: usize, heads: usize, causal: bool, rng: &mut Rng) -> Self {
+        assert_eq!(
+            embed_dim % heads,
+            0,
+            "the embedding size must be divisible by the number of heads"
+        );
+        MultiHeadAttention {
+            query: Linear::new(embed_dim, embed_dim, true, rng),
+            key: Linear::new(embed_dim, embed_dim, true, rng),
+            value: Linear::new(embed_dim, embed_dim, true, rng),
+            output: Linear::new(embed_dim, embed_dim, true, rng),
+            heads,
+            causal,
+        }
+    }
+
+    /// Attends from `[batch, queries, embed_dim]` queries to `[batch, keys,
+    /// embed_dim]` keys and values. `mask` is broadcast to `[batch, heads,
+    /// queries, keys]`, see [`scaled_dot_product_attention`].
+    pub fn attend(
+        &self,
+        query: &Tensor<A>,
+        key: &Tensor<A>,
+        value: &Tensor<A>,
+        mask: Option<&ArrayD<bool>>,
+    ) -> Tensor<A> {
+        let split = |x: &Tensor<A>| {
+            let shape = x.borrow().value.shape().to_vec();
+            assert_eq!(
+                shape.len(),
+                3,
+                "attention expects [batch, length, features] tensors, got shape {shape:?}"
+            );
+            x.reshape(&[shape[0], shape[1], self.heads, shape[2] / self.heads])
+                .permute_axes(&[0, 2, 1, 3])
+        };
+
+        let attended = scaled_dot_product_attention(
+            &split(&self.query.forward(query)),
+            &split(&self.key.forward(key)),
+            &split(&self.value.forward(value)),
+            mask,
+            self.causal,
+        );
+        let shape = attended.borrow().value.shape().to_vec();
+        let merged = attended.permute_axes(&[0, 2, 1, 3]).reshape(&[
+            shape[0],
+            shape[2],
+            shape[1] * shape[3],
+        ]);
+        self.output.forward(&merged)
+    }
+}
+
+impl<A: Element> Module<A> for MultiHeadAttention<A> {
+    /// Applies self-attention to a `[batch, length, embed_dim]` input.
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        self.attend(input, input, input, None)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        let mut parameters = prefix_parameters("query", self.query.named_parameters());
+        parameters.extend(prefix_parameters("key", self.key.named_parameters()));
+        parameters.extend(prefix_parameters("value", self.value.named_parameters()));
+        parameters.extend(prefix_parameters("output", self.output.named_parameters()));
+        parameters
+    }
+}
+
+/// A pre-norm Transformer block: self-attention followed by a two-layer
+/// perceptron, each applied to a normalised input and added back to it.
+pub struct TransformerBlock<A: Element> {
+    pub attention_norm: LayerNorm<A>,
+    pub attention: MultiHeadAttention<A>,
+    pub mlp_norm: LayerNorm<A>,
+    pub mlp: Mlp<A>,
+}
+
+impl<A: Element> TransformerBlock<A> {
+    pub fn new(
+        embed_dim: usize,
+        heads: usize,
+        hidden_dim: usize,
+        causal: bool,
+        rng: &mut Rng,
+    ) -> Self {
+        let eps = constant::<A>(1e-5);
+        TransformerBlock {
+            attention_norm: LayerNorm::new(embed_dim, eps),
+            attention: MultiHeadAttention::new(embed_dim, heads, causal, rng),
+            mlp_norm: LayerNorm::new(embed_dim, eps),
+            mlp: Mlp::new(&[embed_dim, hidden_dim, embed_dim], Activation::Gelu, rng),
+        }
+    }
+
+    /// Applies the block to a `[batch, length, embed_dim]` input, with
+    /// attention restricted by `mask` as in [`MultiHeadAttention::attend`].
+    pub fn forward_masked(&self, input: &Tensor<A>, mask: Option<&ArrayD<bool>>) -> Tensor<A> {
+        let normalised = self.attention_norm.forward(input);
+        let input = input
+            + &self
+                .attention
+                .attend(&normalised, &normalised, &normalised, mask);
+        &input + &self.mlp.forward(&self.mlp_norm.forward(&input))
+    }
+}
+
+impl<A: Element> Module<A> for TransformerBlock<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        self.forward_masked(input, None)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        let mut parameters =
+            prefix_parameters("attention_norm", self.attention_norm.named_parameters());
+        parameters.extend(prefix_parameters(
+            "attention",
+            self.attention.named_parameters(),
+        ));
+        parameters.extend(prefix_parameters(
+            "mlp_norm",
+            self.mlp_norm.named_parameters(),
+        ));
+        parameters.extend(prefix_parameters("mlp", self.mlp.named_parameters()));
+        parameters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, Array, Axis, Ix2};
+
+    use super::*;
+    use crate::{
+        nn::Embedding,
+        testing::{check_tensor_gradients, sequence, weighted},
+    };
+
+    #[test]
+    fn attention_matches_definition() {
+        let query = sequence(&[2, 3, 4]);
+        let key = sequence(&[2, 5, 4]).mapv(f64::sin);
+        let value = sequence(&[2, 5, 2]).mapv(f64::cos);
+        let output = scaled_dot_product_attention(
+            &Tensor::new(query.clone()),
+            &Tensor::new(key.clone()),
+            &Tensor::new(value.clone()),
+            None,
+            false,
+        )
+        .value();
+
+        for b in 0..2 {
+            let q = query
+                .index_axis(Axis(0), b)
+                .into_dimensionality::<Ix2>()
+                .unwrap();
+            let k = key
+                .index_axis(Axis(0), b)
+                .into_dimensionality::<Ix2>()
+                .unwrap();
+            let v = value
+                .index_axis(Axis(0), b)
+                .into_dimensionality::<Ix2>()
+                .unwrap();
+            let weights = (q.dot(&k.t()) / 2.0).mapv(f64::exp);
+            let weights = &weights / &weights.sum_axis(Axis(1)).insert_axis(Axis(1));
+            let expected = weights.dot(&v);
+            let actual = output.index_axis(Axis(0), b);
+            assert!((&actual - &expected.into_dyn())
+                .iter()
+                .all(|d| d.abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn causal_and_padding_masks() {
+        let x = Tensor::new(sequence(&[1, 3, 2]));
+        let value = Tensor::new(array![[[1.0], [10.0], [100.0]]].into_dyn());
+
+        // The first position can only see itself.
+        let causal = scaled_dot_product_attention(&x, &x, &value, None, true).value();
+        assert!((causal[[0, 0, 0]] - 1.0).abs() < 1e-12);
+
+        // Masking out the last key keeps every output below its value.
+        let padding = array![true, true, false].into_dyn();
+        let padded = scaled_dot_product_attention(&x, &x, &value, Some(&padding), false).value();
+        assert!(padded.iter().all(|&y| y < 10.0 + 1e-12));
+    }
+
+    #[test]
+    fn fully_masked_queries() {
+        // The second query of the first sequence can't attend to anything.
+        let x = Tensor::new(sequence(&[2, 3, 4]));
+        let mut mask = ArrayD::from_elem(vec![2, 1, 3, 3], true);
+        mask.slice_mut(ndarray::s![0, 0, 1, ..]).fill(false);
+
+        let head_mask = mask.index_axis(Axis(1), 0).to_owned();
+        let output = scaled_dot_product_attention(&x, &x, &x, Some(&head_mask), false).value();
+        assert!(output
+            .slice(ndarray::s![0, 1, ..])
+            .iter()
+            .all(|&y| y == 0.0));
+
+        let attention = MultiHeadAttention::<f64>::new(4, 2, false, &mut Rng::new(3));
+        weighted(&attention.attend(&x, &x, &x, Some(&mask))).backward();
+        assert!(x.gradient().iter().all(|g| g.is_finite()));
+        for (name, parameter) in attention.named_parameters() {
+            assert!(
+                parameter.gradient().iter().all(|g| g.is_finite()),
+                "{name} has a non-finite gradient"
+            );
+        }
+    }
+
+    #[test]
+    fn finite_differences_attention() {
+        let inputs = [
+            sequence(&[2, 3, 4]),
+            sequence(&[2, 4, 4]).mapv(f64::sin),
+            sequence(&[2, 4, 3]).mapv(f64::cos),
+        ];
+        let padding = array![[[true, true, true, false]], [[true, true, true, true]]].into_dyn();
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&scaled_dot_product_attention(
+                &x[0], &x[1], &x[2], None, false,
+            ))
+        });
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&scaled_dot_product_attention(
+                &x[0],
+                &x[1],
+                &x[2],
+                Some(&padding),
+                true,
+            ))
+        });
+    }
+
+    #[test]
+    fn finite_differences_transformer_block() {
+        let block = TransformerBlock::<f64>::new(4, 2, 8, true, &mut Rng::new(0));
+        check_tensor_gradients(&[sequence(&[2, 3, 4])], |x| weighted(&block.forward(&x[0])));
+    }
+
+    #[test]
+    fn causal_outputs_ignore_the_future() {
+        let block = TransformerBlock::<f64>::new(4, 2, 8, true, &mut Rng::new(1));
+        let input = sequence(&[1, 4, 4]);
+        let mut changed = input.clone();
+        changed[[0, 3, 0]] += 1.0;
+
+        let before = block.forward(&Tensor::new(input)).value();
+        let after = block.forward(&Tensor::new(changed)).value();
+        let prefix = ndarray::s![.., ..3, ..];
+        assert!((&before.slice(prefix) - &after.slice(prefix))
+            .iter()
+            .all(|d| d.abs() < 1e-12));
+        assert!(before[[0, 3, 0]] != after[[0, 3, 0]]);
+    }
+
+    #[test]
+    fn multi_head_attention_names() {
+        let attention = MultiHeadAttention::<f64>::new(4, 2, false, &mut Rng::new(2));
+        let names: Vec<_> = attention
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names.len(), 8);
+        assert_eq!(names[0], "query.weight");
+        assert_eq!(names[7], "output.bias");
+    }
+
+    /// A tiny character-level language model, trained to predict the next
+    /// character of a repeated phrase.
+    #[test]
+    fn character_language_model_learns() {
+        let text: Vec<char> = "hello mkgrad, ".chars().cycle().take(64).collect();
+        let mut vocabulary = text.clone();
+        vocabulary.sort();
+        vocabulary.dedup();
+        let ids: Vec<usize> = text
+            .iter()
+            .map(|c| vocabulary.binary_search(c).unwrap())
+            .collect();
+        let (context, dim) = (8, 16);
+
+        let windows: Vec<_> = (0..ids.len() - context).step_by(3).collect();
+        let inputs = Array::from_shape_fn((windows.len(), context), |(i, t)| ids[windows[i] + t]);
+        let targets: Vec<_> = (0..windows.len())
+            .flat_map(|i| (0..context).map(move |t| (i, t)))
+            .map(|(i, t)| ids[windows[i] + t + 1])
+            .collect();
+
+        let mut rng = Rng::new(3);
+        let tokens = Embedding::<f64>::new(vocabulary.len(), dim, &mut rng);
+        let positions = Embedding::<f64>::new(context, dim, &mut rng);
+        let block = TransformerBlock::new(dim, 2, 2 * dim, true, &mut rng);
+        let head = Linear::new(dim, vocabulary.len(), true, &mut rng);
+        let parameters: Vec<_> = [
+            tokens.parameters(),
+            positions.parameters(),
+            block.parameters(),
+            head.parameters(),
+        ]
+        .concat();
+
+        let position_ids = Array::from_shape_fn((1, context), |(_, t)| t).into_dyn();
+        let loss = || {
+            let x = &tokens.lookup(&inputs.clone().into_dyn()) + &positions.lookup(&position_ids);
+            let logits = head
+                .forward(&block.forward(&x))
+                .reshape(&[targets.len(), vocabulary.len()]);
+            let log_probabilities = logits.log_softmax(1);
+            let picked = ArrayD::from_shape_vec(vec![targets.len(), 1], targets.clone()).unwrap();
+            -log_probabilities.gather(1, &picked).mean(None, false)
+        };
+
+        let initial = loss().value()[[]];
+        for _ in 0..150 {
+            for parameter in &parameters {
+                parameter.zero_grad();
+            }
+            loss().backward();
+            for parameter in &parameters {
+                let step = parameter.gradient() * 0.3;
+                parameter.borrow_mut().value -= &step;
+            }
+        }
+        let last = loss().value()[[]];
+        assert!(last < initial / 4.0, "loss went from {initial} to {last}");
+    }
+}
@@ -0,0 +1,352 @@
+//! Convolution and pooling layers over `[batch, channels, height, width]`
+//! images and `[batch, channels, length]` sequences.
+
+use super::{Module, Rng};
+use crate::{value::constant, Conv1dOptions, Conv2dOptions, Element, Tensor};
+
+/// Draws a weight and optional bias uniformly from `[-1/sqrt(fan_in),
+/// 1/sqrt(fan_in))`, where `fan_in` is the number of inputs to each output.
+fn init<A: Element>(
+    shape: &[usize],
+    fan_in: usize,
+    bias: Option<usize>,
+    rng: &mut Rng,
+) -> (Tensor<A>, Option<Tensor<A>>) {
+    let bound = constant::<A>(1.0) / A::from(fan_in).unwrap().sqrt();
+    (
+        Tensor::new(rng.uniform(shape, -bound, bound)),
+        bias.map(|features| Tensor::new(rng.uniform(&[features], -bound, bound))),
+    )
+}
+
+/// Returns the parameters of a layer with a weight and an optional bias.
+fn weight_and_bias<A: Element>(
+    weight: &Tensor<A>,
+    bias: &Option<Tensor<A>>,
+) -> Vec<(String, Tensor<A>)> {
+    let mut parameters = vec![("weight".to_string(), weight.clone())];
+    if let Some(bias) = bias {
+        parameters.push(("bias".to_string(), bias.clone()));
+    }
+    parameters
+}
+
+/// A 1-dimensional convolution, see [`Tensor::conv1d`].
+pub struct Conv1d<A: Element> {
+    /// The weights, of shape `[out_channels, in_channels / groups, kernel]`.
+    pub weight: Tensor<A>,
+    /// The bias, of shape `[out_channels]`, if the layer has one.
+    pub bias: Option<Tensor<A>>,
+    pub options: Conv1dOptions,
+}
+
+impl<A: Element> Conv1d<A> {
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: usize,
+        options: Conv1dOptions,
+        bias: bool,
+        rng: &mut Rng,
+    ) -> Self {
+        let group_channels = in_channels / options.groups;
+        let (weight, bias) = init(
+            &[out_channels, group_channels, kernel],
+            group_channels * kernel,
+            bias.then_some(out_channels),
+            rng,
+        );
+        Conv1d {
+            weight,
+            bias,
+            options,
+        }
+    }
+}
+
+impl<A: Element> Module<A> for Conv1d<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        input.conv1d(&self.weight, self.bias.as_ref(), self.options)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        weight_and_bias(&self.weight, &self.bias)
+    }
+}
+
+/// A 2-dimensional convolution, see [`Tensor::conv2d`].
+pub struct Conv2d<A: Element> {
+    /// The weights, of shape `[out_channels, in_channels / groups,
+    /// kernel_height, kernel_width]`.
+    pub weight: Tensor<A>,
+    /// The bias, of shape `[out_channels]`, if the layer has one.
+    pub bias: Option<Tensor<A>>,
+    pub options: Conv2dOptions,
+}
+
+impl<A: Element> Conv2d<A> {
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: (usize, usize),
+        options: Conv2dOptions,
+        bias: bool,
+        rng: &mut Rng,
+    ) -> Self {
+        let group_channels = in_channels / options.groups;
+        let (weight, bias) = init(
+            &[out_channels, group_channels, kernel.0, kernel.1],
+            group_channels * kernel.0 * kernel.1,
+            bias.then_some(out_channels),
+            rng,
+        );
+        Conv2d {
+            weight,
+            bias,
+            options,
+        }
+    }
+}
+
+impl<A: Element> Module<A> for Conv2d<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        input.conv2d(&self.weight, self.bias.as_ref(), self.options)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        weight_and_bias(&self.weight, &self.bias)
+    }
+}
+
+/// A transposed 2-dimensional convolution, see [`Tensor::conv_transpose2d`].
+pub struct ConvTranspose2d<A: Element> {
+    /// The weights, of shape `[in_channels, out_channels / groups,
+    /// kernel_height, kernel_width]`.
+    pub weight: Tensor<A>,
+    /// The bias, of shape `[out_channels]`, if the layer has one.
+    pub bias: Option<Tensor<A>>,
+    pub options: Conv2dOptions,
+    pub output_padding: (usize, usize),
+}
+
+impl<A: Element> ConvTranspose2d<A> {
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        kernel: (usize, usize),
+        options: Conv2dOptions,
+        bias: bool,
+        rng: &mut Rng,
+    ) -> Self {
+        let group_outputs = out_channels / options.groups;
+        let (weight, bias) = init(
+            &[in_channels, group_outputs, kernel.0, kernel.1],
+            group_outputs * kernel.0 * kernel.1,
+            bias.then_some(out_channels),
+            rng,
+        );
+        ConvTranspose2d {
+            weight,
+            bias,
+            options,
+            output_padding: (0, 0),
+        }
+    }
+
+    /// Sets the padding added to one side of the output.
+    pub fn with_output_padding(mut self, output_padding: (usize, usize)) -> Self {
+        self.output_padding = output_padding;
+        self
+    }
+}
+
+impl<A: Element> Module<A> for ConvTranspose2d<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        input.conv_transpose2d(
+            &self.weight,
+            self.bias.as_ref(),
+            self.options,
+            self.output_padding,
+        )
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        weight_and_bias(&self.weight, &self.bias)
+    }
+}
+
+/// Max pooling, see [`Tensor::max_pool2d`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxPool2d {
+    pub kernel: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+}
+
+impl MaxPool2d {
+    /// Creates a pooling layer whose windows don't overlap.
+    pub fn new(kernel: (usize, usize)) -> Self {
+        MaxPool2d {
+            kernel,
+            stride: kernel,
+            padding: (0, 0),
+        }
+    }
+}
+
+impl<A: Element> Module<A> for MaxPool2d {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        input.max_pool2d(self.kernel, self.stride, self.padding)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        Vec::new()
+    }
+}
+
+/// Average pooling, see [`Tensor::avg_pool2d`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AvgPool2d {
+    pub kernel: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+}
+
+impl AvgPool2d {
+    /// Creates a pooling layer whose windows don't overlap.
+    pub fn new(kernel: (usize, usize)) -> Self {
+        AvgPool2d {
+            kernel,
+            stride: kernel,
+            padding: (0, 0),
+        }
+    }
+}
+
+impl<A: Element> Module<A> for AvgPool2d {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        input.avg_pool2d(self.kernel, self.stride, self.padding)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        Vec::new()
+    }
+}
+
+/// Average pooling to a fixed output size, see
+/// [`Tensor::adaptive_avg_pool2d`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdaptiveAvgPool2d {
+    pub output_size: (usize, usize),
+}
+
+impl AdaptiveAvgPool2d {
+    pub fn new(output_size: (usize, usize)) -> Self {
+        AdaptiveAvgPool2d { output_size }
+    }
+}
+
+impl<A: Element> Module<A> for AdaptiveAvgPool2d {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        input.adaptive_avg_pool2d(self.output_size)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nn::{Activation, Linear, Sequential},
+        testing::sequence,
+    };
+
+    #[test]
+    fn layer_shapes() {
+        let mut rng = Rng::new(0);
+        let images = Tensor::new(sequence(&[2, 4, 8, 8]));
+        let options = Conv2dOptions {
+            padding: (1, 1),
+            groups: 2,
+            ..Default::default()
+        };
+
+        let conv = Conv2d::<f64>::new(4, 6, (3, 3), options, true, &mut rng);
+        assert_eq!(conv.weight.value().shape(), &[6, 2, 3, 3]);
+        assert_eq!(conv.forward(&images).value().shape(), &[2, 6, 8, 8]);
+
+        let options = Conv2dOptions {
+            stride: (2, 2),
+            padding: (1, 1),
+            ..Default::default()
+        };
+        let up = ConvTranspose2d::<f64>::new(4, 3, (3, 3), options, false, &mut rng)
+            .with_output_padding((1, 1));
+        assert_eq!(up.forward(&images).value().shape(), &[2, 3, 16, 16]);
+        assert_eq!(up.parameters().len(), 1);
+
+        let conv = Conv1d::<f64>::new(4, 5, 3, Conv1dOptions::default(), true, &mut rng);
+        let sequences = Tensor::new(sequence(&[2, 4, 10]));
+        assert_eq!(conv.forward(&sequences).value().shape(), &[2, 5, 8]);
+
+        assert_eq!(
+            MaxPool2d::new((2, 2)).forward(&images).value().shape(),
+            &[2, 4, 4, 4]
+        );
+        assert_eq!(
+            AvgPool2d::new((4, 4)).forward(&images).value().shape(),
+            &[2, 4, 2, 2]
+        );
+        assert_eq!(
+            AdaptiveAvgPool2d::new((3, 1))
+                .forward(&images)
+                .value()
+                .shape(),
+            &[2, 4, 3, 1]
+        );
+    }
+
+    #[test]
+    fn small_cnn_learns() {
+        let mut rng = Rng::new(1);
+        // Tell apart images that are bright on the left from ones bright on
+        // the right.
+        let mut images = rng.uniform(&[8, 1, 6, 6], 0.0, 0.2);
+        let mut labels = ndarray::ArrayD::<f64>::zeros(vec![8, 1]);
+        for i in 0..8 {
+            let columns = if i % 2 == 0 { 0..3 } else { 3..6 };
+            images
+                .slice_mut(ndarray::s![i, 0, .., columns])
+                .mapv_inplace(|x| x + 1.0);
+            labels[[i, 0]] = (i % 2) as f64;
+        }
+        let (images, labels) = (Tensor::new(images), Tensor::new(labels));
+
+        let conv = Conv2d::new(1, 4, (3, 3), Conv2dOptions::default(), true, &mut rng);
+        let head = Linear::new(4, 1, true, &mut rng);
+        let features = Sequential::new()
+            .with(conv)
+            .with(Activation::Relu)
+            .with(AdaptiveAvgPool2d::new((1, 1)));
+
+        let loss = || {
+            let pooled = features.forward(&images).reshape(&[8, 4]);
+            let error = &head.forward(&pooled) - &labels;
+            (&error * &error).mean(None, false)
+        };
+        let initial = loss().value()[[]];
+        for _ in 0..300 {
+            features.zero_grad();
+            head.zero_grad();
+            loss().backward();
+            for parameter in features.parameters().into_iter().chain(head.parameters()) {
+                let step = parameter.gradient() * 0.1;
+                parameter.borrow_mut().value -= &step;
+            }
+        }
+        assert!(loss().value()[[]] < initial / 10.0);
+    }
+}
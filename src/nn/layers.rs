@@ -0,0 +1,585 @@
+//! Standard layers.
+
+use std::cell::RefCell;
+
+use ndarray::{ArrayD, Axis};
+
+use super::{prefix_parameters, Module, Rng};
+use crate::{value::constant, Element, Tensor};
+
+/// Applies `y = x W + b` to the last axis of the input.
+pub struct Linear<A: Element> {
+    /// The weights, of shape `[in_features, out_features]`.
+    pub weight: Tensor<A>,
+    /// The bias, of shape `[out_features]`, if the layer has one.
+    pub bias: Option<Tensor<A>>,
+}
+
+impl<A: Element> Linear<A> {
+    /// Creates a layer with parameters drawn uniformly from
+    /// `[-1/sqrt(in_features), 1/sqrt(in_features))`.
+    pub fn new(in_features: usize, out_features: usize, bias: bool, rng: &mut Rng) -> Self {
+        let bound = constant::<A>(1.0) / A::from(in_features).unwrap().sqrt();
+        Linear {
+            weight: Tensor::new(rng.uniform(&[in_features, out_features], -bound, bound)),
+            bias: bias.then(|| Tensor::new(rng.uniform(&[out_features], -bound, bound))),
+        }
+    }
+}
+
+impl<A: Element> Module<A> for Linear<A> {
+    /// Maps an input of shape `[..., in_features]` to `[..., out_features]`.
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        let shape = input.borrow().value.shape().to_vec();
+        let (in_features, out_features) = {
+            let weight = &self.weight.borrow().value;
+            (weight.shape()[0], weight.shape()[1])
+        };
+
+        let output = if shape.len() == 2 {
+            input.matmul(&self.weight)
+        } else {
+            let mut output_shape = shape[..shape.len() - 1].to_vec();
+            output_shape.push(out_features);
+            input
+                .reshape(&[shape.iter().product::<usize>() / in_features, in_features])
+                .matmul(&self.weight)
+                .reshape(&output_shape)
+        };
+
+        match &self.bias {
+            Some(bias) => &output + bias,
+            None => output,
+        }
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        let mut parameters = vec![("weight".to_string(), self.weight.clone())];
+        if let Some(bias) = &self.bias {
+            parameters.push(("bias".to_string(), bias.clone()));
+        }
+        parameters
+    }
+}
+
+/// An elementwise activation function, usable as a layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activation {
+    Identity,
+    Relu,
+    Tanh,
+    Sigmoid,
+    Gelu,
+    Silu,
+    Softplus,
+}
+
+impl<A: Element> Module<A> for Activation {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        match self {
+            Activation::Identity => input.clone(),
+            Activation::Relu => input.relu(),
+            Activation::Tanh => input.tanh(),
+            Activation::Sigmoid => input.sigmoid(),
+            Activation::Gelu => input.gelu(),
+            Activation::Silu => input.silu(),
+            Activation::Softplus => input.softplus(),
+        }
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        Vec::new()
+    }
+}
+
+/// A multilayer perceptron: [`Linear`] layers with an activation between each
+/// pair of them.
+pub struct Mlp<A: Element> {
+    pub layers: Vec<Linear<A>>,
+    pub activation: Activation,
+}
+
+impl<A: Element> Mlp<A> {
+    /// Creates a perceptron whose layers map between consecutive `sizes`, so
+    /// `[2, 16, 16, 1]` has two hidden layers of 16 units.
+    pub fn new(sizes: &[usize], activation: Activation, rng: &mut Rng) -> Self {
+        assert!(sizes.len() >= 2, "an Mlp needs an input and an output size");
+        Mlp {
+            layers: sizes
+                .windows(2)
+                .map(|sizes| Linear::new(sizes[0], sizes[1], true, rng))
+                .collect(),
+            activation,
+        }
+    }
+}
+
+impl<A: Element> Module<A> for Mlp<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        let mut output = input.clone();
+        for (i, layer) in self.layers.iter().enumerate() {
+            output = layer.forward(&output);
+            if i + 1 < self.layers.len() {
+                output = self.activation.forward(&output);
+            }
+        }
+        output
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, layer)| {
+                prefix_parameters(&format!("layers.{i}"), layer.named_parameters())
+            })
+            .collect()
+    }
+}
+
+/// A lookup table of learned vectors, indexed by integer ids.
+pub struct Embedding<A: Element> {
+    /// The table, of shape `[num_embeddings, embedding_dim]`.
+    pub weight: Tensor<A>,
+}
+
+impl<A: Element> Embedding<A> {
+    /// Creates a table with entries drawn from a standard normal distribution.
+    pub fn new(num_embeddings: usize, embedding_dim: usize, rng: &mut Rng) -> Self {
+        Embedding {
+            weight: Tensor::new(rng.normal(&[num_embeddings, embedding_dim], A::zero(), A::one())),
+        }
+    }
+
+    /// Looks up the vectors for `indices`, giving a tensor of shape
+    /// `[..., embedding_dim]`.
+    pub fn lookup(&self, indices: &ArrayD<usize>) -> Tensor<A> {
+        let flat: Vec<_> = indices.iter().copied().collect();
+        let mut shape = indices.shape().to_vec();
+        shape.push(self.weight.borrow().value.shape()[1]);
+        self.weight.index_select(0, &flat).reshape(&shape)
+    }
+}
+
+impl<A: Element> Module<A> for Embedding<A> {
+    /// Looks up the vectors for an input holding integer ids.
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        let num_embeddings = self.weight.borrow().value.shape()[0];
+        let indices = input.borrow().value.mapv(|id| {
+            let index = id
+                .to_usize()
+                .filter(|&index| id.fract() == A::zero() && index < num_embeddings);
+            index.unwrap_or_else(|| {
+                panic!("embedding id {id:?} is not an integer in 0..{num_embeddings}")
+            })
+        });
+        self.lookup(&indices)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        vec![("weight".to_string(), self.weight.clone())]
+    }
+}
+
+/// Zeroes each element with probability `p` during training, scaling the rest
+/// by `1 / (1 - p)`. Does nothing in evaluation mode.
+pub struct Dropout<A: Element> {
+    pub p: A,
+    training: bool,
+    rng: RefCell<Rng>,
+}
+
+impl<A: Element> Dropout<A> {
+    /// Creates a dropout layer whose masks are drawn from a generator seeded
+    /// with `seed`.
+    pub fn new(p: A, seed: u64) -> Self {
+        assert!(
+            p >= A::zero() && p < A::one(),
+            "dropout probability must be in [0, 1)"
+        );
+        Dropout {
+            p,
+            training: true,
+            rng: RefCell::new(Rng::new(seed)),
+        }
+    }
+}
+
+impl<A: Element> Module<A> for Dropout<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        if !self.training || self.p == A::zero() {
+            return input.clone();
+        }
+
+        let p = self.p.to_f64().unwrap();
+        let scale = (A::one() - self.p).recip();
+        let mut rng = self.rng.borrow_mut();
+        let mask = input
+            .borrow()
+            .value
+            .mapv(|_| if rng.next_f64() < p { A::zero() } else { scale });
+        input * mask
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        Vec::new()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+/// Normalises the last axis of the input to zero mean and unit variance, then
+/// applies a learned scale and shift.
+pub struct LayerNorm<A: Element> {
+    /// The scale, of shape `[features]`.
+    pub weight: Tensor<A>,
+    /// The shift, of shape `[features]`.
+    pub bias: Tensor<A>,
+    pub eps: A,
+}
+
+impl<A: Element> LayerNorm<A> {
+    /// Creates a layer normalising `features` elements, starting as the
+    /// identity scale and shift.
+    pub fn new(features: usize, eps: A) -> Self {
+        LayerNorm {
+            weight: Tensor::new(ArrayD::ones(vec![features])),
+            bias: Tensor::new(ArrayD::zeros(vec![features])),
+            eps,
+        }
+    }
+}
+
+impl<A: Element> Module<A> for LayerNorm<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        let axis = Some(input.borrow().value.ndim() - 1);
+        let mean = input.mean(axis, true);
+        let std = (&input.var(axis, true, A::zero()) + self.eps).sqrt();
+        &(&(&(input - &mean) / &std) * &self.weight) + &self.bias
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        vec![
+            ("weight".to_string(), self.weight.clone()),
+            ("bias".to_string(), self.bias.clone()),
+        ]
+    }
+}
+
+/// Normalises each feature of a `[batch, features]` input over the batch,
+/// then applies a learned scale and shift.
+///
+/// Training uses the statistics of the batch and updates running estimates of
+/// them, which evaluation uses instead.
+pub struct BatchNorm1d<A: Element> {
+    /// The scale, of shape `[features]`.
+    pub weight: Tensor<A>,
+    /// The shift, of shape `[features]`.
+    pub bias: Tensor<A>,
+    pub eps: A,
+    /// How far the running statistics move towards those of each batch.
+    pub momentum: A,
+    running_mean: RefCell<ArrayD<A>>,
+    running_var: RefCell<ArrayD<A>>,
+    training: bool,
+}
+
+impl<A: Element> BatchNorm1d<A> {
+    /// Creates a layer normalising `features` features.
+    pub fn new(features: usize, eps: A, momentum: A) -> Self {
+        BatchNorm1d {
+            weight: Tensor::new(ArrayD::ones(vec![features])),
+            bias: Tensor::new(ArrayD::zeros(vec![features])),
+            eps,
+            momentum,
+            running_mean: RefCell::new(ArrayD::zeros(vec![features])),
+            running_var: RefCell::new(ArrayD::ones(vec![features])),
+            training: true,
+        }
+    }
+
+    /// Returns the running estimate of the mean of each feature.
+    pub fn running_mean(&self) -> ArrayD<A> {
+        self.running_mean.borrow().clone()
+    }
+
+    /// Returns the running estimate of the unbiased variance of each feature.
+    pub fn running_var(&self) -> ArrayD<A> {
+        self.running_var.borrow().clone()
+    }
+}
+
+impl<A: Element> Module<A> for BatchNorm1d<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        let normalized = if self.training {
+            let batch = input.borrow().value.len_of(Axis(0));
+            let mean = input.mean(Some(0), false);
+            let var = input.var(Some(0), false, A::zero());
+
+            let unbiased =
+                var.value() * (A::from(batch).unwrap() / A::from(batch.max(2) - 1).unwrap());
+            let keep = A::one() - self.momentum;
+            let mut running_mean = self.running_mean.borrow_mut();
+            *running_mean = &*running_mean * keep + &mean.value() * self.momentum;
+            let mut running_var = self.running_var.borrow_mut();
+            *running_var = &*running_var * keep + unbiased * self.momentum;
+
+            &(input - &mean) / &(&var + self.eps).sqrt()
+        } else {
+            let scale = (&*self.running_var.borrow() + self.eps).mapv(|x| x.sqrt().recip());
+            &(input - self.running_mean()) * scale
+        };
+        &(&normalized * &self.weight) + &self.bias
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        vec![
+            ("weight".to_string(), self.weight.clone()),
+            ("bias".to_string(), self.bias.clone()),
+        ]
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+/// Chains modules, feeding the output of each into the next.
+pub struct Sequential<A: Element> {
+    pub layers: Vec<Box<dyn Module<A>>>,
+}
+
+impl<A: Element> Sequential<A> {
+    /// Creates an empty chain, which passes its input through unchanged.
+    pub fn new() -> Self {
+        Sequential { layers: Vec::new() }
+    }
+
+    /// Appends a module to the chain.
+    pub fn with(mut self, module: impl Module<A> + 'static) -> Self {
+        self.layers.push(Box::new(module));
+        self
+    }
+}
+
+impl<A: Element> Default for Sequential<A> {
+    fn default() -> Self {
+        Sequential::new()
+    }
+}
+
+impl<A: Element> Module<A> for Sequential<A> {
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        self.layers
+            .iter()
+            .fold(input.clone(), |output, layer| layer.forward(&output))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, layer)| prefix_parameters(&i.to_string(), layer.named_parameters()))
+            .collect()
+    }
+
+    fn set_training(&mut self, training: bool) {
+        for layer in &mut self.layers {
+            layer.set_training(training);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence};
+
+    #[test]
+    fn linear_shapes() {
+        let mut rng = Rng::new(0);
+        let layer = Linear::<f64>::new(3, 4, true, &mut rng);
+
+        assert_eq!(
+            layer
+                .forward(&Tensor::new(sequence(&[5, 3])))
+                .value()
+                .shape(),
+            &[5, 4]
+        );
+        assert_eq!(
+            layer
+                .forward(&Tensor::new(sequence(&[2, 5, 3])))
+                .value()
+                .shape(),
+            &[2, 5, 4]
+        );
+        assert_eq!(layer.parameters().len(), 2);
+        assert_eq!(
+            Linear::<f64>::new(3, 4, false, &mut rng).parameters().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn linear_gradients() {
+        let mut rng = Rng::new(0);
+        let layer = Linear::<f64>::new(3, 2, true, &mut rng);
+        let input = sequence(&[4, 3]);
+        layer.forward(&Tensor::new(input.clone())).backward();
+
+        let column_sums = input.sum_axis(Axis(0)).insert_axis(Axis(1));
+        assert_eq!(
+            layer.weight.gradient(),
+            column_sums.broadcast(vec![3, 2]).unwrap()
+        );
+        assert_eq!(
+            layer.bias.as_ref().unwrap().gradient(),
+            array![4.0, 4.0].into_dyn()
+        );
+
+        check_tensor_gradients(&[input], |x| layer.forward(&x[0]).tanh());
+        check_tensor_gradients(&[sequence(&[2, 2, 3])], |x| layer.forward(&x[0]).tanh());
+    }
+
+    #[test]
+    fn mlp_fits_a_small_dataset() {
+        let mut rng = Rng::new(1);
+        let model = Mlp::<f64>::new(&[2, 8, 1], Activation::Tanh, &mut rng);
+        let inputs = Tensor::new(array![[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]].into_dyn());
+        let targets = array![[0.0], [1.0], [1.0], [0.0]].into_dyn();
+
+        let loss = || {
+            let error = &model.forward(&inputs) - targets.clone();
+            (&error * &error).mean(None, false)
+        };
+        let initial = loss().value()[[]];
+        for _ in 0..500 {
+            model.zero_grad();
+            loss().backward();
+            for parameter in model.parameters() {
+                let gradient = parameter.gradient();
+                parameter.borrow_mut().value.scaled_add(-0.1, &gradient);
+            }
+        }
+
+        assert!(loss().value()[[]] < initial / 10.0);
+    }
+
+    #[test]
+    fn embedding_lookup() {
+        let mut rng = Rng::new(0);
+        let embedding = Embedding::<f64>::new(5, 3, &mut rng);
+        let table = embedding.weight.value();
+
+        let output = embedding.lookup(&array![[4, 1], [1, 0]].into_dyn());
+        assert_eq!(output.value().shape(), &[2, 2, 3]);
+        assert_eq!(
+            output.value().slice(ndarray::s![0, 0, ..]),
+            table.slice(ndarray::s![4, ..])
+        );
+
+        output.backward();
+        assert_eq!(
+            embedding.weight.gradient().sum_axis(Axis(1)),
+            array![3.0, 6.0, 0.0, 0.0, 3.0].into_dyn()
+        );
+
+        let ids = Tensor::new(array![2.0, 2.0].into_dyn());
+        assert_eq!(embedding.forward(&ids).value().shape(), &[2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "embedding id 1.5 is not an integer in 0..5")]
+    fn embedding_rejects_fractional_ids() {
+        let embedding = Embedding::<f64>::new(5, 3, &mut Rng::new(0));
+        embedding.forward(&Tensor::new(array![1.5].into_dyn()));
+    }
+
+    #[test]
+    #[should_panic(expected = "embedding id 5.0 is not an integer in 0..5")]
+    fn embedding_rejects_out_of_range_ids() {
+        let embedding = Embedding::<f64>::new(5, 3, &mut Rng::new(0));
+        embedding.forward(&Tensor::new(array![5.0].into_dyn()));
+    }
+
+    #[test]
+    fn dropout_is_seeded_and_respects_mode() {
+        let input = Tensor::new(ArrayD::<f64>::ones(vec![10_000]));
+        let mut dropout = Dropout::new(0.25, 3);
+
+        let output = dropout.forward(&input).value();
+        let zeros = output.iter().filter(|&&x| x == 0.0).count();
+        assert!((zeros as f64 / 10_000.0 - 0.25).abs() < 0.02);
+        assert!(output.iter().all(|&x| x == 0.0 || x == 1.0 / 0.75));
+        assert_eq!(Dropout::new(0.25, 3).forward(&input).value(), output);
+
+        dropout.eval();
+        assert!(dropout.forward(&input).ptr_eq(&input));
+    }
+
+    #[test]
+    fn layer_norm_normalises_last_axis() {
+        let layer = LayerNorm::<f64>::new(4, 1e-5);
+        let output = layer.forward(&Tensor::new(sequence(&[3, 4]))).value();
+
+        for row in output.outer_iter() {
+            assert!(row.mean().unwrap().abs() < 1e-9);
+            assert!((row.var(0.0) - 1.0).abs() < 1e-3);
+        }
+
+        check_tensor_gradients(&[sequence(&[3, 4])], |x| layer.forward(&x[0]).powi(3));
+    }
+
+    #[test]
+    fn batch_norm_tracks_running_statistics() {
+        let mut layer = BatchNorm1d::<f64>::new(2, 1e-5, 0.1);
+        let input = array![[1.0, 10.0], [3.0, 20.0], [5.0, 30.0]].into_dyn();
+
+        let output = layer.forward(&Tensor::new(input.clone())).value();
+        for column in output.axis_iter(Axis(1)) {
+            assert!(column.mean().unwrap().abs() < 1e-9);
+        }
+        let running_mean = layer.running_mean();
+        assert!((running_mean[0] - 0.3).abs() < 1e-12);
+        assert!((running_mean[1] - 2.0).abs() < 1e-12);
+        let running_var = layer.running_var();
+        assert!((running_var[0] - (0.9 + 0.1 * 4.0)).abs() < 1e-12);
+        assert!((running_var[1] - (0.9 + 0.1 * 100.0)).abs() < 1e-12);
+
+        layer.eval();
+        let output = layer.forward(&Tensor::new(input.clone())).value();
+        let expected = (input.clone() - &running_mean) / (running_var + 1e-5).mapv(f64::sqrt);
+        assert!((output - expected).iter().all(|x| x.abs() < 1e-12));
+        assert_eq!(layer.running_mean(), running_mean);
+
+        layer.train();
+        check_tensor_gradients(&[sequence(&[4, 2])], |x| layer.forward(&x[0]).powi(3));
+    }
+
+    #[test]
+    fn sequential_chains_and_names_layers() {
+        let mut rng = Rng::new(0);
+        let mut model = Sequential::<f64>::new()
+            .with(Linear::new(3, 4, true, &mut rng))
+            .with(Activation::Relu)
+            .with(Dropout::new(0.5, 0))
+            .with(Linear::new(4, 1, false, &mut rng));
+
+        let names: Vec<_> = model
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["0.weight", "0.bias", "3.weight"]);
+
+        model.eval();
+        let input = Tensor::new(sequence(&[2, 3]));
+        assert_eq!(model.forward(&input).value(), model.forward(&input).value());
+        assert_eq!(model.forward(&input).value().shape(), &[2, 1]);
+    }
+}
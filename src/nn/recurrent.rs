@@ -0,0 +1,340 @@
+//! Recurrent cells and helpers to unroll them over sequences.
+//!
+//! A cell maps one `[batch, input_size]` step and the previous state to the
+//! next state. Backpropagation through time is just `backward` on the unrolled
+//! graph, which can be arbitrarily long.
+
+use ndarray::ArrayD;
+
+use super::{prefix_parameters, Linear, Module, Rng};
+use crate::{Element, Tensor};
+
+/// A module that carries a state from one step of a sequence to the next.
+pub trait Cell<A: Element>: Module<A> {
+    /// The state passed between steps.
+    type State: Clone;
+
+    /// Returns the all-zero state for a batch of the given size.
+    fn zero_state(&self, batch: usize) -> Self::State;
+
+    /// Computes the state after reading the `[batch, input_size]` input.
+    fn step(&self, input: &Tensor<A>, state: &Self::State) -> Self::State;
+
+    /// Returns the `[batch, hidden_size]` output of a state.
+    fn hidden(state: &Self::State) -> Tensor<A>;
+}
+
+/// Runs `cell` over `inputs` starting from `state`, returning the output of
+/// every step and the final state.
+pub fn unroll<A: Element, C: Cell<A>>(
+    cell: &C,
+    inputs: &[Tensor<A>],
+    state: C::State,
+) -> (Vec<Tensor<A>>, C::State) {
+    let mut state = state;
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        state = cell.step(input, &state);
+        outputs.push(C::hidden(&state));
+    }
+    (outputs, state)
+}
+
+/// Runs `cell` from the zero state over a `[length, batch, input_size]`
+/// sequence, returning the `[length, batch, hidden_size]` outputs. An empty
+/// sequence gives empty outputs.
+pub fn unroll_sequence<A: Element, C: Cell<A>>(cell: &C, sequence: &Tensor<A>) -> Tensor<A> {
+    let (length, batch) = {
+        let shape = sequence.borrow().value.shape().to_vec();
+        assert_eq!(
+            shape.len(),
+            3,
+            "recurrent cells expect a [length, batch, features] sequence, got shape {shape:?}"
+        );
+        (shape[0], shape[1])
+    };
+    if length == 0 {
+        let hidden_size = C::hidden(&cell.zero_state(batch)).borrow().value.shape()[1];
+        return Tensor::new(ArrayD::zeros(vec![0, batch, hidden_size]));
+    }
+
+    let inputs: Vec<_> = sequence
+        .chunk(length, 0)
+        .iter()
+        .map(|step| step.squeeze(0))
+        .collect();
+
+    let (outputs, _) = unroll(cell, &inputs, cell.zero_state(batch));
+    Tensor::stack(&outputs, 0)
+}
+
+/// Returns the number of output features of a layer.
+fn output_size<A: Element>(layer: &Linear<A>) -> usize {
+    layer.weight.borrow().value.shape()[1]
+}
+
+/// Returns the parameters of a cell's input and hidden projections.
+fn projection_parameters<A: Element>(
+    input: &Linear<A>,
+    hidden: &Linear<A>,
+) -> Vec<(String, Tensor<A>)> {
+    let mut parameters = prefix_parameters("input", input.named_parameters());
+    parameters.extend(prefix_parameters("hidden", hidden.named_parameters()));
+    parameters
+}
+
+/// An Elman cell: `h' = tanh(x W_x + b_x + h W_h + b_h)`.
+pub struct RnnCell<A: Element> {
+    pub input: Linear<A>,
+    pub hidden: Linear<A>,
+}
+
+impl<A: Element> RnnCell<A> {
+    pub fn new(input_size: usize, hidden_size: usize, rng: &mut Rng) -> Self {
+        RnnCell {
+            input: Linear::new(input_size, hidden_size, true, rng),
+            hidden: Linear::new(hidden_size, hidden_size, true, rng),
+        }
+    }
+}
+
+impl<A: Element> Cell<A> for RnnCell<A> {
+    type State = Tensor<A>;
+
+    fn zero_state(&self, batch: usize) -> Self::State {
+        Tensor::new(ArrayD::zeros(vec![batch, output_size(&self.hidden)]))
+    }
+
+    fn step(&self, input: &Tensor<A>, state: &Self::State) -> Self::State {
+        (&self.input.forward(input) + &self.hidden.forward(state)).tanh()
+    }
+
+    fn hidden(state: &Self::State) -> Tensor<A> {
+        state.clone()
+    }
+}
+
+impl<A: Element> Module<A> for RnnCell<A> {
+    /// Runs the cell over a `[length, batch, input_size]` sequence, see
+    /// [`unroll_sequence`].
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        unroll_sequence(self, input)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        projection_parameters(&self.input, &self.hidden)
+    }
+}
+
+/// A gated recurrent unit. The projections hold the reset, update and
+/// candidate gates side by side, in that order.
+pub struct GruCell<A: Element> {
+    pub input: Linear<A>,
+    pub hidden: Linear<A>,
+}
+
+impl<A: Element> GruCell<A> {
+    pub fn new(input_size: usize, hidden_size: usize, rng: &mut Rng) -> Self {
+        GruCell {
+            input: Linear::new(input_size, 3 * hidden_size, true, rng),
+            hidden: Linear::new(hidden_size, 3 * hidden_size, true, rng),
+        }
+    }
+}
+
+impl<A: Element> Cell<A> for GruCell<A> {
+    type State = Tensor<A>;
+
+    fn zero_state(&self, batch: usize) -> Self::State {
+        Tensor::new(ArrayD::zeros(vec![batch, output_size(&self.hidden) / 3]))
+    }
+
+    fn step(&self, input: &Tensor<A>, state: &Self::State) -> Self::State {
+        let x = self.input.forward(input).chunk(3, 1);
+        let h = self.hidden.forward(state).chunk(3, 1);
+
+        let reset = (&x[0] + &h[0]).sigmoid();
+        let update = (&x[1] + &h[1]).sigmoid();
+        let candidate = (&x[2] + &(&reset * &h[2])).tanh();
+        // (1 - z) n + z h, written to reuse the difference.
+        &candidate + &(&update * &(state - &candidate))
+    }
+
+    fn hidden(state: &Self::State) -> Tensor<A> {
+        state.clone()
+    }
+}
+
+impl<A: Element> Module<A> for GruCell<A> {
+    /// Runs the cell over a `[length, batch, input_size]` sequence, see
+    /// [`unroll_sequence`].
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        unroll_sequence(self, input)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        projection_parameters(&self.input, &self.hidden)
+    }
+}
+
+/// A long short-term memory cell, whose state is the pair `(hidden, cell)`.
+/// The projections hold the input, forget, candidate and output gates side by
+/// side, in that order.
+pub struct LstmCell<A: Element> {
+    pub input: Linear<A>,
+    pub hidden: Linear<A>,
+}
+
+impl<A: Element> LstmCell<A> {
+    pub fn new(input_size: usize, hidden_size: usize, rng: &mut Rng) -> Self {
+        LstmCell {
+            input: Linear::new(input_size, 4 * hidden_size, true, rng),
+            hidden: Linear::new(hidden_size, 4 * hidden_size, true, rng),
+        }
+    }
+}
+
+impl<A: Element> Cell<A> for LstmCell<A> {
+    type State = (Tensor<A>, Tensor<A>);
+
+    fn zero_state(&self, batch: usize) -> Self::State {
+        let zeros = ArrayD::zeros(vec![batch, output_size(&self.hidden) / 4]);
+        (Tensor::new(zeros.clone()), Tensor::new(zeros))
+    }
+
+    fn step(&self, input: &Tensor<A>, (hidden, cell): &Self::State) -> Self::State {
+        let gates = &self.input.forward(input) + &self.hidden.forward(hidden);
+        let gates = gates.chunk(4, 1);
+
+        let input_gate = gates[0].sigmoid();
+        let forget_gate = gates[1].sigmoid();
+        let candidate = gates[2].tanh();
+        let output_gate = gates[3].sigmoid();
+
+        let cell = &(&forget_gate * cell) + &(&input_gate * &candidate);
+        (&output_gate * &cell.tanh(), cell)
+    }
+
+    fn hidden((hidden, _): &Self::State) -> Tensor<A> {
+        hidden.clone()
+    }
+}
+
+impl<A: Element> Module<A> for LstmCell<A> {
+    /// Runs the cell over a `[length, batch, input_size]` sequence, see
+    /// [`unroll_sequence`].
+    fn forward(&self, input: &Tensor<A>) -> Tensor<A> {
+        unroll_sequence(self, input)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<A>)> {
+        projection_parameters(&self.input, &self.hidden)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence, weighted};
+
+    const LENGTH: usize = 40;
+
+    /// Gives the tests access to the projections of every cell.
+    trait HasProjections {
+        fn projections(&mut self) -> (&mut Linear<f64>, &mut Linear<f64>);
+    }
+
+    macro_rules! has_projections {
+        ($($cell:ident),*) => {$(
+            impl HasProjections for $cell<f64> {
+                fn projections(&mut self) -> (&mut Linear<f64>, &mut Linear<f64>) {
+                    (&mut self.input, &mut self.hidden)
+                }
+            }
+        )*};
+    }
+
+    has_projections!(RnnCell, GruCell, LstmCell);
+
+    /// Checks the gradients of the inputs and parameters of the cells built by
+    /// `new` through a long unroll against finite differences.
+    fn check_cell<C: Cell<f64> + HasProjections>(new: impl Fn() -> C) {
+        let mut inputs = vec![sequence(&[LENGTH, 2, 3]).mapv(f64::sin)];
+        inputs.extend(new().parameters().iter().map(|parameter| parameter.value()));
+
+        check_tensor_gradients(&inputs, |x| {
+            let mut cell = new();
+            let (input, hidden) = cell.projections();
+            input.weight = x[1].clone();
+            input.bias = Some(x[2].clone());
+            hidden.weight = x[3].clone();
+            hidden.bias = Some(x[4].clone());
+            weighted(&cell.forward(&x[0]).narrow(0, LENGTH - 3, 3))
+        });
+    }
+
+    #[test]
+    fn finite_differences_rnn_cell() {
+        check_cell(|| RnnCell::new(3, 3, &mut Rng::new(0)));
+    }
+
+    #[test]
+    fn finite_differences_gru_cell() {
+        check_cell(|| GruCell::new(3, 3, &mut Rng::new(1)));
+    }
+
+    #[test]
+    fn finite_differences_lstm_cell() {
+        check_cell(|| LstmCell::new(3, 3, &mut Rng::new(2)));
+    }
+
+    #[test]
+    fn shapes_and_names() {
+        let mut rng = Rng::new(3);
+        let cell = LstmCell::<f64>::new(3, 5, &mut rng);
+        let input = Tensor::new(sequence(&[7, 2, 3]));
+
+        assert_eq!(cell.forward(&input).value().shape(), &[7, 2, 5]);
+        let names: Vec<_> = cell
+            .named_parameters()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["input.weight", "input.bias", "hidden.weight", "hidden.bias"]
+        );
+
+        let steps: Vec<_> = (0..4)
+            .map(|_| Tensor::new(ArrayD::ones(vec![2, 3])))
+            .collect();
+        let gru = GruCell::<f64>::new(3, 5, &mut rng);
+        let (outputs, state) = unroll(&gru, &steps, gru.zero_state(2));
+        assert_eq!(outputs.len(), 4);
+        assert!(outputs[3].ptr_eq(&state));
+    }
+
+    #[test]
+    fn empty_sequences() {
+        let cell = GruCell::<f64>::new(3, 5, &mut Rng::new(5));
+        let input = Tensor::new(ArrayD::zeros(vec![0, 2, 3]));
+
+        assert_eq!(cell.forward(&input).value().shape(), &[0, 2, 5]);
+    }
+
+    #[test]
+    fn backward_through_a_very_long_unroll() {
+        let cell = RnnCell::<f64>::new(1, 2, &mut Rng::new(4));
+        let input = Tensor::new(ArrayD::ones(vec![1, 1]));
+        let steps = vec![input.clone(); 20_000];
+
+        let (_, state) = unroll(&cell, &steps, cell.zero_state(1));
+        state.sum(None, false).backward();
+
+        assert!(input.gradient().iter().all(|g| g.is_finite()));
+        assert!(cell
+            .parameters()
+            .iter()
+            .all(|parameter| parameter.gradient().iter().any(|&g| g != 0.0)));
+    }
+}
@@ -0,0 +1,79 @@
+//! Seedable random number generation for initialising parameters.
+
+use ndarray::ArrayD;
+
+use crate::Element;
+
+/// A small, seedable pseudo-random number generator (SplitMix64), so that
+/// parameter initialisation and dropout masks are reproducible.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Returns the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number uniformly distributed in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns an array of numbers uniformly distributed in `[low, high)`.
+    pub fn uniform<A: Element>(&mut self, shape: &[usize], low: A, high: A) -> ArrayD<A> {
+        ArrayD::from_shape_simple_fn(shape, || {
+            low + (high - low) * A::from(self.next_f64()).unwrap()
+        })
+    }
+
+    /// Returns an array of normally distributed numbers.
+    pub fn normal<A: Element>(&mut self, shape: &[usize], mean: A, std: A) -> ArrayD<A> {
+        ArrayD::from_shape_simple_fn(shape, || {
+            // Box-Muller transform, using 1 - u to keep the logarithm finite.
+            let radius = (-2.0 * (1.0 - self.next_f64()).ln()).sqrt();
+            let angle = 2.0 * std::f64::consts::PI * self.next_f64();
+            mean + std * A::from(radius * angle.cos()).unwrap()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_sequences_repeat() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let mut c = Rng::new(8);
+
+        let first: Vec<_> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..4).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(first, (0..4).map(|_| c.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn distributions() {
+        let mut rng = Rng::new(0);
+
+        let uniform = rng.uniform::<f64>(&[10_000], -2.0, 3.0);
+        assert!(uniform.iter().all(|&x| (-2.0..3.0).contains(&x)));
+        assert!((uniform.mean().unwrap() - 0.5).abs() < 0.05);
+
+        let normal = rng.normal::<f64>(&[10_000], 1.0, 2.0);
+        assert!((normal.mean().unwrap() - 1.0).abs() < 0.1);
+        assert!((normal.std(0.0) - 2.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,195 @@
+//! Optimisers that update parameters from their gradients.
+//!
+//! An optimiser owns handles to the parameters it updates, usually taken from
+//! [`Module::parameters`](crate::nn::Module::parameters). A training step is
+//! then `zero_grad`, `backward` on the loss and `step`.
+
+mod adam;
+mod adaptive;
+mod clip;
+mod schedule;
+mod sgd;
+
+pub use adam::Adam;
+pub use adaptive::{Adadelta, Adagrad, RmsProp};
+pub use clip::{clip_grad_norm, clip_grad_value, grad_norm};
+pub use schedule::{
+    LrScheduler, PlateauMode, PlateauState, ReduceOnPlateau, Schedule, SchedulerState,
+};
+pub use sgd::Sgd;
+
+use ndarray::ArrayD;
+
+use crate::{Element, Tensor};
+
+/// Updates a set of parameters from their gradients.
+pub trait Optimizer<A: Element> {
+    /// Returns the parameters updated by the optimiser.
+    fn parameters(&self) -> &[Tensor<A>];
+
+    /// Returns the current learning rate.
+    fn learning_rate(&self) -> A;
+
+    /// Changes the learning rate used by the following steps.
+    fn set_learning_rate(&mut self, learning_rate: A);
+
+    /// Updates every parameter from its current gradient.
+    fn step(&mut self);
+
+    /// Returns a copy of everything the optimiser carries between steps.
+    fn state(&self) -> OptimizerState<A>;
+
+    /// Restores a state returned by [`Optimizer::state`] on an optimiser of
+    /// the same kind over parameters of the same shapes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the state is missing a buffer or a buffer has the wrong
+    /// shape.
+    fn load_state(&mut self, state: OptimizerState<A>);
+
+    /// Resets the gradients of all the parameters to zero.
+    fn zero_grad(&self) {
+        for parameter in self.parameters() {
+            parameter.zero_grad();
+        }
+    }
+}
+
+/// The state of an optimiser as plain data, to checkpoint and restore it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptimizerState<A> {
+    /// The number of steps taken so far.
+    pub steps: usize,
+    pub learning_rate: A,
+    /// Named buffers, such as running averages, holding one array per
+    /// parameter in the order of [`Optimizer::parameters`].
+    pub buffers: Vec<(String, Vec<ArrayD<A>>)>,
+}
+
+impl<A: Element> OptimizerState<A> {
+    /// Removes the buffer called `name`, checking that it matches `parameters`.
+    fn take_buffer(&mut self, name: &str, parameters: &[Tensor<A>]) -> Vec<ArrayD<A>> {
+        let position = self
+            .buffers
+            .iter()
+            .position(|(buffer, _)| buffer == name)
+            .unwrap_or_else(|| panic!("optimizer state has no `{name}` buffer"));
+        let (_, buffer) = self.buffers.remove(position);
+
+        assert_eq!(
+            buffer.len(),
+            parameters.len(),
+            "`{name}` buffer has the wrong number of parameters"
+        );
+        for (array, parameter) in buffer.iter().zip(parameters) {
+            assert_eq!(
+                array.shape(),
+                parameter.borrow().value.shape(),
+                "`{name}` buffer doesn't match the shape of its parameter"
+            );
+        }
+        buffer
+    }
+}
+
+/// Returns a zero buffer for every parameter.
+fn zeros<A: Element>(parameters: &[Tensor<A>]) -> Vec<ArrayD<A>> {
+    parameters
+        .iter()
+        .map(|parameter| ArrayD::zeros(parameter.borrow().value.raw_dim()))
+        .collect()
+}
+
+/// Moves a parameter by `learning_rate` against `update`.
+fn descend<A: Element>(parameter: &Tensor<A>, update: &ArrayD<A>, learning_rate: A) {
+    parameter
+        .borrow_mut()
+        .value
+        .zip_mut_with(update, |p, &u| *p = *p - learning_rate * u);
+}
+
+/// Returns the gradient of a parameter with an L2 penalty of `weight_decay`
+/// added to the loss.
+fn decayed_gradient<A: Element>(parameter: &Tensor<A>, weight_decay: A) -> ArrayD<A> {
+    let node = parameter.borrow();
+    if weight_decay == A::zero() {
+        node.gradient.clone()
+    } else {
+        &node.gradient + &(&node.value * weight_decay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    /// Minimises `sum((x - target)² * scales)`, a badly conditioned quadratic,
+    /// and returns the final distance to the minimum.
+    pub fn minimise<O: Optimizer<f64>>(
+        make: impl FnOnce(Vec<Tensor<f64>>) -> O,
+        steps: usize,
+    ) -> f64 {
+        let x = Tensor::new(array![3.0, -2.0, 0.5].into_dyn());
+        let target = Tensor::new(array![1.0, 1.0, -1.0].into_dyn());
+        let scales = Tensor::new(array![1.0, 4.0, 0.25].into_dyn());
+        let mut optimizer = make(vec![x.clone()]);
+
+        for _ in 0..steps {
+            optimizer.zero_grad();
+            let difference = &x - &target;
+            (&(&difference * &difference) * &scales)
+                .sum(None, false)
+                .backward();
+            optimizer.step();
+        }
+        (x.value() - target.value()).mapv(f64::abs).sum()
+    }
+
+    /// Checks that an optimiser restored from the state of another continues
+    /// exactly like it.
+    pub fn check_state_round_trip<O: Optimizer<f64>>(make: impl Fn(Vec<Tensor<f64>>) -> O) {
+        let run = |optimizer: &mut O, x: &Tensor<f64>, steps: usize| {
+            for _ in 0..steps {
+                optimizer.zero_grad();
+                (&x.sin() * &x.sin()).sum(None, false).backward();
+                optimizer.step();
+            }
+        };
+
+        let x = Tensor::new(array![0.5, -1.0, 2.0].into_dyn());
+        let mut original = make(vec![x.clone()]);
+        run(&mut original, &x, 3);
+
+        let y = Tensor::new(x.value());
+        let mut restored = make(vec![y.clone()]);
+        restored.load_state(original.state());
+        assert_eq!(restored.state(), original.state());
+
+        run(&mut original, &x, 3);
+        run(&mut restored, &y, 3);
+        assert_eq!(x.value(), y.value());
+    }
+
+    #[test]
+    fn zero_grad_resets_parameters() {
+        let x = Tensor::new(array![1.0, 2.0].into_dyn());
+        let optimizer = Sgd::new(vec![x.clone()], 0.1);
+        (&x * &x).sum(None, false).backward();
+
+        optimizer.zero_grad();
+
+        assert_eq!(x.gradient(), array![0.0, 0.0].into_dyn());
+    }
+
+    #[test]
+    #[should_panic(expected = "optimizer state has no `momentum` buffer")]
+    fn loading_the_wrong_state_panics() {
+        let x = Tensor::new(array![1.0, 2.0].into_dyn());
+        let adam = Adam::new(vec![x.clone()], 0.1);
+        let mut sgd = Sgd::new(vec![x], 0.1).with_momentum(0.9);
+        sgd.load_state(adam.state());
+    }
+}
@@ -0,0 +1,215 @@
+//! Adam and its variants.
+
+use ndarray::{ArrayD, Zip};
+
+use super::{decayed_gradient, descend, zeros, Optimizer, OptimizerState};
+use crate::{value::constant, Element, Tensor};
+
+/// Adam, which scales each step by running estimates of the first and second
+/// moments of the gradient.
+///
+/// Weight decay is added to the gradient, as an L2 penalty would be, unless
+/// `decoupled_weight_decay` is set, which gives AdamW.
+pub struct Adam<A: Element> {
+    parameters: Vec<Tensor<A>>,
+    learning_rate: A,
+    /// The decay rates of the first and second moment estimates.
+    pub betas: (A, A),
+    /// Added to the denominator for numerical stability.
+    pub eps: A,
+    pub weight_decay: A,
+    /// Whether weight decay shrinks the parameters directly instead of being
+    /// added to the gradient.
+    pub decoupled_weight_decay: bool,
+    /// Whether to divide by the largest second moment estimate seen so far.
+    pub amsgrad: bool,
+    steps: usize,
+    first_moment: Vec<ArrayD<A>>,
+    second_moment: Vec<ArrayD<A>>,
+    max_second_moment: Vec<ArrayD<A>>,
+}
+
+impl<A: Element> Adam<A> {
+    /// Creates Adam with betas of `(0.9, 0.999)`, an epsilon of `1e-8` and no
+    /// weight decay.
+    pub fn new(parameters: Vec<Tensor<A>>, learning_rate: A) -> Self {
+        Adam {
+            first_moment: zeros(&parameters),
+            second_moment: zeros(&parameters),
+            max_second_moment: zeros(&parameters),
+            parameters,
+            learning_rate,
+            betas: (constant::<A>(0.9), constant::<A>(0.999)),
+            eps: constant::<A>(1e-8),
+            weight_decay: A::zero(),
+            decoupled_weight_decay: false,
+            amsgrad: false,
+            steps: 0,
+        }
+    }
+
+    /// Creates AdamW: Adam with decoupled weight decay.
+    pub fn adamw(parameters: Vec<Tensor<A>>, learning_rate: A, weight_decay: A) -> Self {
+        let mut adam = Adam::new(parameters, learning_rate);
+        adam.weight_decay = weight_decay;
+        adam.decoupled_weight_decay = true;
+        adam
+    }
+
+    /// Sets the decay rates of the first and second moment estimates.
+    pub fn with_betas(mut self, betas: (A, A)) -> Self {
+        self.betas = betas;
+        self
+    }
+
+    /// Sets the constant added to the denominator.
+    pub fn with_eps(mut self, eps: A) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    /// Sets the strength of the weight decay, which is added to the gradient
+    /// unless the decay is decoupled.
+    pub fn with_weight_decay(mut self, weight_decay: A) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    /// Divides by the largest second moment estimate seen so far, which keeps
+    /// the steps from growing again (AMSGrad).
+    pub fn with_amsgrad(mut self) -> Self {
+        self.amsgrad = true;
+        self
+    }
+}
+
+impl<A: Element> Optimizer<A> for Adam<A> {
+    fn parameters(&self) -> &[Tensor<A>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> A {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: A) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn step(&mut self) {
+        self.steps += 1;
+        let (beta1, beta2) = self.betas;
+        let steps = self.steps as i32;
+        let first_correction = A::one() - beta1.powi(steps);
+        let second_correction = A::one() - beta2.powi(steps);
+
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let gradient = if self.decoupled_weight_decay {
+                parameter.gradient()
+            } else {
+                decayed_gradient(parameter, self.weight_decay)
+            };
+
+            let first = &mut self.first_moment[i];
+            *first = &*first * beta1 + &gradient * (A::one() - beta1);
+            let second = &mut self.second_moment[i];
+            *second = &*second * beta2 + gradient.mapv(|g| g * g) * (A::one() - beta2);
+            let second = if self.amsgrad {
+                let max = &mut self.max_second_moment[i];
+                max.zip_mut_with(second, |max, &v| *max = max.max(v));
+                &*max
+            } else {
+                &*second
+            };
+
+            let mut update = ArrayD::zeros(gradient.raw_dim());
+            Zip::from(&mut update)
+                .and(&self.first_moment[i])
+                .and(second)
+                .for_each(|update, &m, &v| {
+                    *update = m / first_correction / ((v / second_correction).sqrt() + self.eps);
+                });
+
+            if self.decoupled_weight_decay {
+                let decay = A::one() - self.learning_rate * self.weight_decay;
+                parameter.borrow_mut().value.mapv_inplace(|p| p * decay);
+            }
+            descend(parameter, &update, self.learning_rate);
+        }
+    }
+
+    fn state(&self) -> OptimizerState<A> {
+        let mut buffers = vec![
+            ("first_moment".to_string(), self.first_moment.clone()),
+            ("second_moment".to_string(), self.second_moment.clone()),
+        ];
+        if self.amsgrad {
+            buffers.push((
+                "max_second_moment".to_string(),
+                self.max_second_moment.clone(),
+            ));
+        }
+        OptimizerState {
+            steps: self.steps,
+            learning_rate: self.learning_rate,
+            buffers,
+        }
+    }
+
+    fn load_state(&mut self, mut state: OptimizerState<A>) {
+        self.first_moment = state.take_buffer("first_moment", &self.parameters);
+        self.second_moment = state.take_buffer("second_moment", &self.parameters);
+        if self.amsgrad {
+            self.max_second_moment = state.take_buffer("max_second_moment", &self.parameters);
+        }
+        self.steps = state.steps;
+        self.learning_rate = state.learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::optim::tests::{check_state_round_trip, minimise};
+
+    #[test]
+    fn first_step_moves_by_the_learning_rate() {
+        // With bias correction, the first step is the sign of the gradient.
+        let x = Tensor::new(array![1.0_f64, -2.0].into_dyn());
+        let mut adam = Adam::new(vec![x.clone()], 0.1);
+        (&x * &x).sum(None, false).backward();
+        adam.step();
+
+        let expected = array![0.9, -1.9].into_dyn();
+        assert!((x.value() - expected).iter().all(|d| d.abs() < 1e-6));
+    }
+
+    #[test]
+    fn adamw_decays_without_gradient() {
+        let x = Tensor::new(array![2.0].into_dyn());
+        let mut adamw = Adam::adamw(vec![x.clone()], 0.1, 0.5);
+        adamw.step();
+        assert_eq!(x.value(), array![1.9].into_dyn());
+
+        // Coupled decay goes through the moments instead.
+        let y = Tensor::new(array![2.0_f64].into_dyn());
+        let mut adam = Adam::new(vec![y.clone()], 0.1).with_weight_decay(0.5);
+        adam.step();
+        assert!((y.value()[0] - 1.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn converges() {
+        assert!(minimise(|p| Adam::new(p, 0.1), 500) < 1e-2);
+        assert!(minimise(|p| Adam::new(p, 0.1).with_amsgrad(), 500) < 1e-2);
+        assert!(minimise(|p| Adam::adamw(p, 0.1, 1e-4), 500) < 1e-2);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        check_state_round_trip(|p| Adam::new(p, 0.1));
+        check_state_round_trip(|p| Adam::new(p, 0.1).with_amsgrad());
+    }
+}
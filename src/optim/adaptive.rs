@@ -0,0 +1,354 @@
+//! Optimisers that adapt the step of each element to the history of its
+//! gradients.
+
+use ndarray::{ArrayD, Zip};
+
+use super::{decayed_gradient, descend, zeros, Optimizer, OptimizerState};
+use crate::{value::constant, Element, Tensor};
+
+/// RMSProp, which divides each step by a running root mean square of the
+/// gradient.
+pub struct RmsProp<A: Element> {
+    parameters: Vec<Tensor<A>>,
+    learning_rate: A,
+    /// The decay rate of the running averages.
+    pub alpha: A,
+    /// Added to the denominator for numerical stability.
+    pub eps: A,
+    pub momentum: A,
+    /// Whether to normalise by an estimate of the variance of the gradient
+    /// instead of its second moment.
+    pub centered: bool,
+    pub weight_decay: A,
+    steps: usize,
+    square_average: Vec<ArrayD<A>>,
+    gradient_average: Vec<ArrayD<A>>,
+    momentum_buffer: Vec<ArrayD<A>>,
+}
+
+impl<A: Element> RmsProp<A> {
+    /// Creates RMSProp with an alpha of `0.99`, an epsilon of `1e-8` and no
+    /// momentum or weight decay.
+    pub fn new(parameters: Vec<Tensor<A>>, learning_rate: A) -> Self {
+        RmsProp {
+            square_average: zeros(&parameters),
+            gradient_average: zeros(&parameters),
+            momentum_buffer: zeros(&parameters),
+            parameters,
+            learning_rate,
+            alpha: constant::<A>(0.99),
+            eps: constant::<A>(1e-8),
+            momentum: A::zero(),
+            centered: false,
+            weight_decay: A::zero(),
+            steps: 0,
+        }
+    }
+
+    /// Sets the decay rate of the running averages.
+    pub fn with_alpha(mut self, alpha: A) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Accumulates the scaled steps into a buffer that decays by `momentum`
+    /// every step.
+    pub fn with_momentum(mut self, momentum: A) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    /// Normalises by an estimate of the variance of the gradient instead of
+    /// its second moment.
+    pub fn with_centered(mut self) -> Self {
+        self.centered = true;
+        self
+    }
+
+    /// Adds an L2 penalty of strength `weight_decay` to the gradient.
+    pub fn with_weight_decay(mut self, weight_decay: A) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl<A: Element> Optimizer<A> for RmsProp<A> {
+    fn parameters(&self) -> &[Tensor<A>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> A {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: A) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn step(&mut self) {
+        let alpha = self.alpha;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let gradient = decayed_gradient(parameter, self.weight_decay);
+
+            let square = &mut self.square_average[i];
+            *square = &*square * alpha + gradient.mapv(|g| g * g) * (A::one() - alpha);
+            let mut denominator = square.clone();
+            if self.centered {
+                let average = &mut self.gradient_average[i];
+                *average = &*average * alpha + &gradient * (A::one() - alpha);
+                denominator.zip_mut_with(average, |d, &g| *d = *d - g * g);
+            }
+            denominator.mapv_inplace(|d| d.sqrt() + self.eps);
+
+            let mut update = gradient / denominator;
+            if self.momentum != A::zero() {
+                let buffer = &mut self.momentum_buffer[i];
+                *buffer = &*buffer * self.momentum + &update;
+                update = buffer.clone();
+            }
+            descend(parameter, &update, self.learning_rate);
+        }
+        self.steps += 1;
+    }
+
+    fn state(&self) -> OptimizerState<A> {
+        OptimizerState {
+            steps: self.steps,
+            learning_rate: self.learning_rate,
+            buffers: vec![
+                ("square_average".to_string(), self.square_average.clone()),
+                (
+                    "gradient_average".to_string(),
+                    self.gradient_average.clone(),
+                ),
+                ("momentum_buffer".to_string(), self.momentum_buffer.clone()),
+            ],
+        }
+    }
+
+    fn load_state(&mut self, mut state: OptimizerState<A>) {
+        self.square_average = state.take_buffer("square_average", &self.parameters);
+        self.gradient_average = state.take_buffer("gradient_average", &self.parameters);
+        self.momentum_buffer = state.take_buffer("momentum_buffer", &self.parameters);
+        self.steps = state.steps;
+        self.learning_rate = state.learning_rate;
+    }
+}
+
+/// Adagrad, which divides each step by the root of the sum of all the squared
+/// gradients so far.
+pub struct Adagrad<A: Element> {
+    parameters: Vec<Tensor<A>>,
+    learning_rate: A,
+    /// Added to the denominator for numerical stability.
+    pub eps: A,
+    pub weight_decay: A,
+    steps: usize,
+    square_sum: Vec<ArrayD<A>>,
+}
+
+impl<A: Element> Adagrad<A> {
+    /// Creates Adagrad with an epsilon of `1e-10` and no weight decay.
+    pub fn new(parameters: Vec<Tensor<A>>, learning_rate: A) -> Self {
+        Adagrad {
+            square_sum: zeros(&parameters),
+            parameters,
+            learning_rate,
+            eps: constant::<A>(1e-10),
+            weight_decay: A::zero(),
+            steps: 0,
+        }
+    }
+
+    /// Adds an L2 penalty of strength `weight_decay` to the gradient.
+    pub fn with_weight_decay(mut self, weight_decay: A) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl<A: Element> Optimizer<A> for Adagrad<A> {
+    fn parameters(&self) -> &[Tensor<A>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> A {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: A) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn step(&mut self) {
+        for (parameter, sum) in self.parameters.iter().zip(&mut self.square_sum) {
+            let gradient = decayed_gradient(parameter, self.weight_decay);
+            sum.zip_mut_with(&gradient, |sum, &g| *sum += g * g);
+
+            let mut update = gradient;
+            update.zip_mut_with(sum, |update, &sum| {
+                *update = *update / (sum.sqrt() + self.eps);
+            });
+            descend(parameter, &update, self.learning_rate);
+        }
+        self.steps += 1;
+    }
+
+    fn state(&self) -> OptimizerState<A> {
+        OptimizerState {
+            steps: self.steps,
+            learning_rate: self.learning_rate,
+            buffers: vec![("square_sum".to_string(), self.square_sum.clone())],
+        }
+    }
+
+    fn load_state(&mut self, mut state: OptimizerState<A>) {
+        self.square_sum = state.take_buffer("square_sum", &self.parameters);
+        self.steps = state.steps;
+        self.learning_rate = state.learning_rate;
+    }
+}
+
+/// Adadelta, which scales each step by the ratio of running root mean squares
+/// of past updates and past gradients, so that it needs little tuning of the
+/// learning rate.
+pub struct Adadelta<A: Element> {
+    parameters: Vec<Tensor<A>>,
+    learning_rate: A,
+    /// The decay rate of the running averages.
+    pub rho: A,
+    /// Added inside the roots for numerical stability.
+    pub eps: A,
+    pub weight_decay: A,
+    steps: usize,
+    square_average: Vec<ArrayD<A>>,
+    update_average: Vec<ArrayD<A>>,
+}
+
+impl<A: Element> Adadelta<A> {
+    /// Creates Adadelta with a rho of `0.9`, an epsilon of `1e-6` and no
+    /// weight decay. The usual learning rate is 1.
+    pub fn new(parameters: Vec<Tensor<A>>, learning_rate: A) -> Self {
+        Adadelta {
+            square_average: zeros(&parameters),
+            update_average: zeros(&parameters),
+            parameters,
+            learning_rate,
+            rho: constant::<A>(0.9),
+            eps: constant::<A>(1e-6),
+            weight_decay: A::zero(),
+            steps: 0,
+        }
+    }
+
+    /// Sets the decay rate of the running averages.
+    pub fn with_rho(mut self, rho: A) -> Self {
+        self.rho = rho;
+        self
+    }
+
+    /// Adds an L2 penalty of strength `weight_decay` to the gradient.
+    pub fn with_weight_decay(mut self, weight_decay: A) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl<A: Element> Optimizer<A> for Adadelta<A> {
+    fn parameters(&self) -> &[Tensor<A>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> A {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: A) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn step(&mut self) {
+        let (rho, eps) = (self.rho, self.eps);
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let gradient = decayed_gradient(parameter, self.weight_decay);
+            let mut update = ArrayD::zeros(gradient.raw_dim());
+            Zip::from(&mut update)
+                .and(&gradient)
+                .and(&mut self.square_average[i])
+                .and(&mut self.update_average[i])
+                .for_each(|update, &g, square, previous| {
+                    *square = *square * rho + g * g * (A::one() - rho);
+                    *update = ((*previous + eps) / (*square + eps)).sqrt() * g;
+                    *previous = *previous * rho + *update * *update * (A::one() - rho);
+                });
+            descend(parameter, &update, self.learning_rate);
+        }
+        self.steps += 1;
+    }
+
+    fn state(&self) -> OptimizerState<A> {
+        OptimizerState {
+            steps: self.steps,
+            learning_rate: self.learning_rate,
+            buffers: vec![
+                ("square_average".to_string(), self.square_average.clone()),
+                ("update_average".to_string(), self.update_average.clone()),
+            ],
+        }
+    }
+
+    fn load_state(&mut self, mut state: OptimizerState<A>) {
+        self.square_average = state.take_buffer("square_average", &self.parameters);
+        self.update_average = state.take_buffer("update_average", &self.parameters);
+        self.steps = state.steps;
+        self.learning_rate = state.learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::optim::tests::{check_state_round_trip, minimise};
+
+    #[test]
+    fn first_steps() {
+        // Adagrad's first step is the learning rate times the sign of the
+        // gradient.
+        let x = Tensor::new(array![1.0_f64, -2.0].into_dyn());
+        let mut adagrad = Adagrad::new(vec![x.clone()], 0.5);
+        (&x * &x).sum(None, false).backward();
+        adagrad.step();
+        assert!((x.value() - array![0.5, -1.5])
+            .iter()
+            .all(|d| d.abs() < 1e-9));
+
+        // RMSProp's first step divides by sqrt(1 - alpha) |g|.
+        let y = Tensor::new(array![1.0_f64].into_dyn());
+        let mut rmsprop = RmsProp::new(vec![y.clone()], 0.01).with_alpha(0.75);
+        y.sum(None, false).backward();
+        rmsprop.step();
+        assert!((y.value()[0] - 0.98).abs() < 1e-9);
+    }
+
+    #[test]
+    fn converges() {
+        assert!(minimise(|p| RmsProp::new(p, 0.01), 1000) < 1e-1);
+        assert!(
+            minimise(
+                |p| RmsProp::new(p, 0.01).with_centered().with_momentum(0.5),
+                1000
+            ) < 1e-1
+        );
+        assert!(minimise(|p| Adagrad::new(p, 0.5), 1000) < 1e-3);
+        assert!(minimise(|p| Adadelta::new(p, 1.0), 3000) < 1e-1);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        check_state_round_trip(|p| RmsProp::new(p, 0.01).with_centered().with_momentum(0.5));
+        check_state_round_trip(|p| Adagrad::new(p, 0.1));
+        check_state_round_trip(|p| Adadelta::new(p, 1.0));
+    }
+}
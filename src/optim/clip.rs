@@ -0,0 +1,129 @@
+//! Measuring and clipping the gradients of a set of parameters.
+
+use crate::{value::constant, Element, Tensor};
+
+/// Returns the `norm_type`-norm of the gradients of all the parameters taken
+/// together, as if they were concatenated into one vector. A `norm_type` of
+/// infinity gives the largest absolute gradient. A `NaN` gradient makes the
+/// norm `NaN`, so that divergence can be detected.
+pub fn grad_norm<A: Element>(parameters: &[Tensor<A>], norm_type: A) -> A {
+    assert!(
+        norm_type > A::zero(),
+        "grad_norm needs a positive norm_type, got {norm_type:?}"
+    );
+    if norm_type.is_infinite() {
+        return parameters.iter().fold(A::zero(), |max, parameter| {
+            parameter.borrow().gradient.fold(max, |max, &g| {
+                let g = g.abs();
+                if g.is_nan() || g > max {
+                    g
+                } else {
+                    max
+                }
+            })
+        });
+    }
+
+    let total = parameters.iter().fold(A::zero(), |total, parameter| {
+        parameter
+            .borrow()
+            .gradient
+            .fold(total, |total, &g| total + g.abs().powf(norm_type))
+    });
+    total.powf(norm_type.recip())
+}
+
+/// Rescales the gradients of the parameters so that their joint
+/// `norm_type`-norm (see [`grad_norm`]) is at most `max_norm`, keeping their
+/// direction. Returns the norm from before clipping.
+pub fn clip_grad_norm<A: Element>(parameters: &[Tensor<A>], max_norm: A, norm_type: A) -> A {
+    let norm = grad_norm(parameters, norm_type);
+    let scale = max_norm / (norm + constant::<A>(1e-6));
+    if scale < A::one() {
+        for parameter in parameters {
+            parameter.borrow_mut().gradient.mapv_inplace(|g| g * scale);
+        }
+    }
+    norm
+}
+
+/// Clamps every gradient element of the parameters to `[-clip_value,
+/// clip_value]`.
+pub fn clip_grad_value<A: Element>(parameters: &[Tensor<A>], clip_value: A) {
+    for parameter in parameters {
+        parameter
+            .borrow_mut()
+            .gradient
+            .mapv_inplace(|g| g.max(-clip_value).min(clip_value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    /// Returns parameters whose gradients are `[3, -4]` and `[12]`.
+    fn parameters() -> Vec<Tensor<f64>> {
+        let x = Tensor::new(array![1.5, -2.0].into_dyn());
+        let y = Tensor::new(array![6.0].into_dyn());
+        (&x * &x).sum(None, false).backward();
+        (&y * &y).sum(None, false).backward();
+        vec![x, y]
+    }
+
+    #[test]
+    fn norms() {
+        let parameters = parameters();
+        assert_eq!(grad_norm(&parameters, 2.0), 13.0);
+        assert_eq!(grad_norm(&parameters, 1.0), 19.0);
+        assert_eq!(grad_norm(&parameters, f64::INFINITY), 12.0);
+    }
+
+    #[test]
+    fn nan_gradients_give_a_nan_norm() {
+        let parameters = parameters();
+        parameters[0].borrow_mut().gradient[0] = f64::NAN;
+        assert!(grad_norm(&parameters, 2.0).is_nan());
+        assert!(grad_norm(&parameters, f64::INFINITY).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "grad_norm needs a positive norm_type, got -inf")]
+    fn negative_infinity_is_not_a_norm() {
+        grad_norm(&parameters(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn clipping_the_norm_keeps_the_direction() {
+        let parameters = parameters();
+        assert_eq!(clip_grad_norm(&parameters, 6.5, 2.0), 13.0);
+
+        assert!((grad_norm(&parameters, 2.0) - 6.5).abs() < 1e-6);
+        let expected = [array![1.5, -2.0], array![6.0]];
+        for (parameter, expected) in parameters.iter().zip(expected) {
+            assert!((parameter.gradient() - expected)
+                .iter()
+                .all(|d| d.abs() < 1e-6));
+        }
+    }
+
+    #[test]
+    fn small_gradients_are_left_alone() {
+        let parameters = parameters();
+        assert_eq!(clip_grad_norm(&parameters, 20.0, 2.0), 13.0);
+        assert_eq!(parameters[0].gradient(), array![3.0, -4.0].into_dyn());
+
+        assert_eq!(clip_grad_norm(&parameters, 6.0, f64::INFINITY), 12.0);
+        assert!((grad_norm(&parameters, f64::INFINITY) - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clipping_values() {
+        let parameters = parameters();
+        clip_grad_value(&parameters, 3.5);
+        assert_eq!(parameters[0].gradient(), array![3.0, -3.5].into_dyn());
+        assert_eq!(parameters[1].gradient(), array![3.5].into_dyn());
+    }
+}
@@ -0,0 +1,576 @@
+//! Learning-rate schedules.
+//!
+//! A [`Schedule`] maps a step number to a factor of the optimiser's base
+//! learning rate, and an [`LrScheduler`] applies it step by step.
+//! [`ReduceOnPlateau`] instead reacts to a metric reported after each epoch.
+//! Everything here is plain data, so it can be stored next to an
+//! [`OptimizerState`](super::OptimizerState) and restored with it.
+
+use super::Optimizer;
+use crate::{value::constant, Element};
+
+/// A learning-rate factor as a function of the step number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule<A> {
+    /// Always 1.
+    Constant,
+    /// Multiplies the factor by `gamma` every `step_size` steps.
+    Step { step_size: usize, gamma: A },
+    /// Multiplies the factor by `gamma` every step.
+    Exponential { gamma: A },
+    /// Anneals from 1 to `min_factor` along a half cosine over `period`
+    /// steps, then restarts with a period `period_mult` times longer.
+    CosineWarmRestarts {
+        period: usize,
+        period_mult: usize,
+        min_factor: A,
+    },
+    /// Rises linearly from `start_factor` to 1 over `steps` steps, then stays
+    /// at 1.
+    LinearWarmup { steps: usize, start_factor: A },
+    /// Rises from `1 / div_factor` to 1 over the first `pct_start` of
+    /// `total_steps`, then falls to `1 / (div_factor * final_div_factor)`,
+    /// both along half cosines.
+    OneCycle {
+        total_steps: usize,
+        pct_start: A,
+        div_factor: A,
+        final_div_factor: A,
+    },
+    /// Follows `first` for `steps` steps, then `then` counting from zero.
+    Then {
+        first: Box<Schedule<A>>,
+        steps: usize,
+        then: Box<Schedule<A>>,
+    },
+    /// Multiplies the factors of two schedules.
+    Product(Box<Schedule<A>>, Box<Schedule<A>>),
+}
+
+/// Interpolates from `start` at `progress` 0 to `end` at `progress` 1 along a
+/// half cosine.
+fn cosine<A: Element>(start: A, end: A, progress: A) -> A {
+    end + (start - end) * (A::one() + (A::PI() * progress).cos()) / constant::<A>(2.0)
+}
+
+impl<A: Element> Schedule<A> {
+    /// Returns the factor of the base learning rate at `step`.
+    pub fn factor(&self, step: usize) -> A {
+        let from = |n: usize| A::from(n).unwrap();
+        match self {
+            Schedule::Constant => A::one(),
+            Schedule::Step { step_size, gamma } => {
+                assert!(
+                    *step_size >= 1,
+                    "Schedule::Step needs a step_size of at least 1"
+                );
+                gamma.powi((step / step_size) as i32)
+            }
+            Schedule::Exponential { gamma } => gamma.powi(step as i32),
+            Schedule::CosineWarmRestarts {
+                period,
+                period_mult,
+                min_factor,
+            } => {
+                assert!(
+                    *period >= 1 && *period_mult >= 1,
+                    "Schedule::CosineWarmRestarts needs a period and period_mult of at least 1"
+                );
+                let (mut start, mut period) = (step, *period);
+                if *period_mult == 1 {
+                    start %= period;
+                } else {
+                    // The periods grow geometrically, so this only takes a
+                    // logarithmic number of restarts to pass `step`.
+                    while start >= period {
+                        start -= period;
+                        period *= period_mult;
+                    }
+                }
+                cosine(A::one(), *min_factor, from(start) / from(period))
+            }
+            Schedule::LinearWarmup {
+                steps,
+                start_factor,
+            } => {
+                if step >= *steps {
+                    A::one()
+                } else {
+                    *start_factor + (A::one() - *start_factor) * from(step) / from(*steps)
+                }
+            }
+            Schedule::OneCycle {
+                total_steps,
+                pct_start,
+                div_factor,
+                final_div_factor,
+            } => {
+                let initial = div_factor.recip();
+                let last = initial / *final_div_factor;
+                let peak = (*pct_start * from(*total_steps))
+                    .round()
+                    .to_usize()
+                    .unwrap()
+                    .max(1);
+                let end = total_steps.saturating_sub(1).max(peak + 1);
+                if step <= peak {
+                    cosine(initial, A::one(), from(step) / from(peak))
+                } else {
+                    let progress = from(step.min(end) - peak) / from(end - peak);
+                    cosine(A::one(), last, progress)
+                }
+            }
+            Schedule::Then { first, steps, then } => {
+                if step < *steps {
+                    first.factor(step)
+                } else {
+                    then.factor(step - steps)
+                }
+            }
+            Schedule::Product(a, b) => a.factor(step) * b.factor(step),
+        }
+    }
+
+    /// Follows this schedule for `steps` steps, then `next`, as in a warmup
+    /// followed by cosine annealing.
+    pub fn then(self, steps: usize, next: Schedule<A>) -> Self {
+        Schedule::Then {
+            first: Box::new(self),
+            steps,
+            then: Box::new(next),
+        }
+    }
+
+    /// Multiplies this schedule by `other`.
+    pub fn times(self, other: Schedule<A>) -> Self {
+        Schedule::Product(Box::new(self), Box::new(other))
+    }
+}
+
+/// Drives the learning rate of an optimiser along a [`Schedule`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LrScheduler<A> {
+    pub schedule: Schedule<A>,
+    base_learning_rate: A,
+    steps: usize,
+}
+
+/// The state of an [`LrScheduler`] as plain data, enough to rebuild it with
+/// [`LrScheduler::from_state`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchedulerState<A> {
+    pub schedule: Schedule<A>,
+    pub steps: usize,
+    pub base_learning_rate: A,
+}
+
+impl<A: Element> LrScheduler<A> {
+    /// Takes the optimiser's current learning rate as the base and sets it to
+    /// the rate for step 0.
+    pub fn new<O: Optimizer<A> + ?Sized>(schedule: Schedule<A>, optimizer: &mut O) -> Self {
+        let scheduler = LrScheduler {
+            schedule,
+            base_learning_rate: optimizer.learning_rate(),
+            steps: 0,
+        };
+        optimizer.set_learning_rate(scheduler.learning_rate());
+        scheduler
+    }
+
+    /// Returns the learning rate for the current step.
+    pub fn learning_rate(&self) -> A {
+        self.base_learning_rate * self.schedule.factor(self.steps)
+    }
+
+    /// Moves to the next step, updating the optimiser's learning rate. Call it
+    /// after each optimiser step.
+    pub fn step<O: Optimizer<A> + ?Sized>(&mut self, optimizer: &mut O) {
+        self.steps += 1;
+        optimizer.set_learning_rate(self.learning_rate());
+    }
+
+    /// Rebuilds a scheduler from a state returned by [`LrScheduler::state`].
+    /// The optimiser's learning rate isn't touched, as it is restored with the
+    /// optimiser's own state.
+    pub fn from_state(state: SchedulerState<A>) -> Self {
+        LrScheduler {
+            schedule: state.schedule,
+            base_learning_rate: state.base_learning_rate,
+            steps: state.steps,
+        }
+    }
+
+    /// Returns a copy of the schedule and the progress along it.
+    pub fn state(&self) -> SchedulerState<A> {
+        SchedulerState {
+            schedule: self.schedule.clone(),
+            steps: self.steps,
+            base_learning_rate: self.base_learning_rate,
+        }
+    }
+
+    /// Restores a state returned by [`LrScheduler::state`], schedule
+    /// included.
+    pub fn load_state(&mut self, state: SchedulerState<A>) {
+        *self = LrScheduler::from_state(state);
+    }
+}
+
+/// Whether a metric watched by [`ReduceOnPlateau`] should go down or up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlateauMode {
+    Min,
+    Max,
+}
+
+/// Multiplies the learning rate by `factor` when a reported metric hasn't
+/// improved for more than `patience` reports.
+///
+/// It changes the optimiser's learning rate directly, so it shouldn't drive
+/// the same optimiser as an [`LrScheduler`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReduceOnPlateau<A> {
+    pub mode: PlateauMode,
+    pub factor: A,
+    pub patience: usize,
+    /// The relative change that counts as an improvement.
+    pub threshold: A,
+    /// The number of reports to wait after a reduction before counting
+    /// reports without improvement again.
+    pub cooldown: usize,
+    pub min_learning_rate: A,
+    state: PlateauState<A>,
+}
+
+/// The progress of a [`ReduceOnPlateau`] as plain data. It doesn't include
+/// the settings, such as `factor` and `patience`, which the caller rebuilds
+/// along with the scheduler before loading the state.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlateauState<A> {
+    /// The best metric reported so far.
+    pub best: Option<A>,
+    /// The number of reports since the last improvement.
+    pub bad_reports: usize,
+    pub cooldown_left: usize,
+}
+
+impl<A: Element> ReduceOnPlateau<A> {
+    /// Creates a scheduler with a threshold of `1e-4`, no cooldown and no
+    /// minimum learning rate.
+    pub fn new(mode: PlateauMode, factor: A, patience: usize) -> Self {
+        ReduceOnPlateau {
+            mode,
+            factor,
+            patience,
+            threshold: constant::<A>(1e-4),
+            cooldown: 0,
+            min_learning_rate: A::zero(),
+            state: PlateauState {
+                best: None,
+                bad_reports: 0,
+                cooldown_left: 0,
+            },
+        }
+    }
+
+    /// Sets the relative change that counts as an improvement.
+    pub fn with_threshold(mut self, threshold: A) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Waits `cooldown` reports after each reduction before counting reports
+    /// without improvement again.
+    pub fn with_cooldown(mut self, cooldown: usize) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Never reduces the learning rate below `min_learning_rate`.
+    pub fn with_min_learning_rate(mut self, min_learning_rate: A) -> Self {
+        self.min_learning_rate = min_learning_rate;
+        self
+    }
+
+    /// Reports the latest value of the metric, reducing the optimiser's
+    /// learning rate if it has plateaued. Returns whether it was reduced.
+    pub fn step<O: Optimizer<A> + ?Sized>(&mut self, optimizer: &mut O, metric: A) -> bool {
+        let state = &mut self.state;
+        let improved = match state.best {
+            None => true,
+            Some(best) => match self.mode {
+                PlateauMode::Min => metric < best - best.abs() * self.threshold,
+                PlateauMode::Max => metric > best + best.abs() * self.threshold,
+            },
+        };
+        if improved {
+            state.best = Some(metric);
+            state.bad_reports = 0;
+        } else {
+            state.bad_reports += 1;
+        }
+
+        if state.cooldown_left > 0 {
+            state.cooldown_left -= 1;
+            state.bad_reports = 0;
+        }
+        if state.bad_reports <= self.patience {
+            return false;
+        }
+
+        state.bad_reports = 0;
+        state.cooldown_left = self.cooldown;
+        let current = optimizer.learning_rate();
+        let reduced = (current * self.factor).max(self.min_learning_rate);
+        optimizer.set_learning_rate(reduced);
+        reduced < current
+    }
+
+    /// Returns a copy of the progress tracked between reports.
+    pub fn state(&self) -> PlateauState<A> {
+        self.state
+    }
+
+    /// Restores progress returned by [`ReduceOnPlateau::state`].
+    pub fn load_state(&mut self, state: PlateauState<A>) {
+        self.state = state;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::{
+        optim::{Adam, Sgd},
+        Tensor,
+    };
+
+    fn factors(schedule: &Schedule<f64>, steps: usize) -> Vec<f64> {
+        (0..steps).map(|step| schedule.factor(step)).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() < 1e-12,
+                "expected {expected:?}, got {actual:?}"
+            );
+        }
+    }
+
+    fn sgd() -> Sgd<f64> {
+        Sgd::new(vec![Tensor::new(array![1.0].into_dyn())], 0.1)
+    }
+
+    #[test]
+    fn decays() {
+        let step = Schedule::Step {
+            step_size: 2,
+            gamma: 0.5,
+        };
+        assert_close(&factors(&step, 5), &[1.0, 1.0, 0.5, 0.5, 0.25]);
+
+        let exponential = Schedule::Exponential { gamma: 0.5 };
+        assert_close(&factors(&exponential, 3), &[1.0, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn cosine_warm_restarts() {
+        let schedule = Schedule::CosineWarmRestarts {
+            period: 2,
+            period_mult: 2,
+            min_factor: 0.0,
+        };
+        // Periods of 2 then 4 steps.
+        assert_close(
+            &factors(&schedule, 7),
+            &[
+                1.0,
+                0.5,
+                1.0,
+                0.5 + 0.5 / 2.0_f64.sqrt(),
+                0.5,
+                0.5 - 0.5 / 2.0_f64.sqrt(),
+                1.0,
+            ],
+        );
+
+        let fixed = Schedule::CosineWarmRestarts {
+            period: 2,
+            period_mult: 1,
+            min_factor: 0.0,
+        };
+        assert_close(&factors(&fixed, 4), &[1.0, 0.5, 1.0, 0.5]);
+        assert_eq!(fixed.factor(usize::MAX - 1), 1.0);
+    }
+
+    #[test]
+    fn warmup_then_cosine() {
+        let schedule = Schedule::LinearWarmup {
+            steps: 4,
+            start_factor: 0.0,
+        }
+        .then(
+            4,
+            Schedule::CosineWarmRestarts {
+                period: 2,
+                period_mult: 1,
+                min_factor: 0.2,
+            },
+        );
+        assert_close(
+            &factors(&schedule, 8),
+            &[0.0, 0.25, 0.5, 0.75, 1.0, 0.6, 1.0, 0.6],
+        );
+
+        let scaled = Schedule::Exponential { gamma: 0.5 }.times(schedule);
+        assert_close(&factors(&scaled, 3), &[0.0, 0.125, 0.125]);
+    }
+
+    #[test]
+    fn one_cycle() {
+        let schedule = Schedule::OneCycle {
+            total_steps: 11,
+            pct_start: 0.2,
+            div_factor: 10.0,
+            final_div_factor: 100.0,
+        };
+        let factors = factors(&schedule, 12);
+        assert_close(&factors[..3], &[0.1, 0.55, 1.0]);
+        assert!(factors[3..]
+            .windows(2)
+            .all(|pair| pair[1] < pair[0] || pair[1] == 1e-3));
+        assert_close(&factors[10..], &[1e-3, 1e-3]);
+    }
+
+    #[test]
+    fn scheduler_drives_the_optimizer() {
+        let mut optimizer = sgd();
+        let mut scheduler = LrScheduler::new(
+            Schedule::LinearWarmup {
+                steps: 2,
+                start_factor: 0.5,
+            },
+            &mut optimizer,
+        );
+        assert_eq!(optimizer.learning_rate(), 0.05);
+        scheduler.step(&mut optimizer);
+        assert!((optimizer.learning_rate() - 0.075).abs() < 1e-12);
+
+        // A scheduler loaded into one with another schedule takes the saved
+        // schedule along with the progress.
+        let mut other = sgd();
+        let mut restored = LrScheduler::new(Schedule::Constant, &mut other);
+        restored.load_state(scheduler.state());
+        scheduler.step(&mut optimizer);
+        restored.step(&mut other);
+        assert_eq!(restored, scheduler);
+        assert_eq!(other.learning_rate(), 0.1);
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let schedule = Schedule::LinearWarmup {
+            steps: 3,
+            start_factor: 0.1,
+        }
+        .then(
+            5,
+            Schedule::CosineWarmRestarts {
+                period: 4,
+                period_mult: 2,
+                min_factor: 0.0,
+            },
+        );
+        let x = Tensor::new(array![1.0, -2.0].into_dyn());
+        let mut optimizer = Adam::new(vec![x.clone()], 0.1);
+        let mut scheduler = LrScheduler::new(schedule, &mut optimizer);
+        let train =
+            |optimizer: &mut Adam<f64>, scheduler: &mut LrScheduler<f64>, x: &Tensor<f64>| {
+                for _ in 0..4 {
+                    optimizer.zero_grad();
+                    (x * x).sum(None, false).backward();
+                    optimizer.step();
+                    scheduler.step(optimizer);
+                }
+            };
+        train(&mut optimizer, &mut scheduler, &x);
+
+        // Save both states, then rebuild everything from them alone.
+        let checkpoint = (optimizer.state(), scheduler.state(), x.value());
+        let y = Tensor::new(checkpoint.2.clone());
+        let mut restored_optimizer = Adam::new(vec![y.clone()], 1.0);
+        restored_optimizer.load_state(checkpoint.0);
+        let mut restored_scheduler = LrScheduler::from_state(checkpoint.1);
+
+        train(&mut optimizer, &mut scheduler, &x);
+        train(&mut restored_optimizer, &mut restored_scheduler, &y);
+        assert_eq!(restored_scheduler, scheduler);
+        assert_eq!(restored_optimizer.state(), optimizer.state());
+        assert_eq!(y.value(), x.value());
+    }
+
+    #[test]
+    #[should_panic(expected = "Schedule::Step needs a step_size of at least 1")]
+    fn step_size_must_be_positive() {
+        Schedule::Step {
+            step_size: 0,
+            gamma: 0.5,
+        }
+        .factor(3);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Schedule::CosineWarmRestarts needs a period and period_mult of at least 1"
+    )]
+    fn period_mult_must_be_positive() {
+        Schedule::CosineWarmRestarts {
+            period: 2,
+            period_mult: 0,
+            min_factor: 0.0,
+        }
+        .factor(5);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Schedule::CosineWarmRestarts needs a period and period_mult of at least 1"
+    )]
+    fn period_must_be_positive() {
+        LrScheduler::new(
+            Schedule::CosineWarmRestarts {
+                period: 0,
+                period_mult: 1,
+                min_factor: 0.0,
+            },
+            &mut sgd(),
+        );
+    }
+
+    #[test]
+    fn reduce_on_plateau() {
+        let mut optimizer = sgd();
+        let mut scheduler = ReduceOnPlateau::new(PlateauMode::Min, 0.5, 1)
+            .with_cooldown(1)
+            .with_min_learning_rate(0.02);
+
+        let reductions: Vec<_> = [1.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5]
+            .into_iter()
+            .map(|metric| scheduler.step(&mut optimizer, metric))
+            .collect();
+        assert_eq!(
+            reductions,
+            [false, false, false, true, false, false, true, false, false, true]
+        );
+        assert_eq!(optimizer.learning_rate(), 0.02);
+        assert!(!scheduler.step(&mut optimizer, 0.1));
+
+        let mut restored = ReduceOnPlateau::new(PlateauMode::Min, 0.5, 1);
+        restored.load_state(scheduler.state());
+        assert_eq!(restored.state().best, Some(0.1));
+    }
+}
@@ -0,0 +1,152 @@
+//! Stochastic gradient descent.
+
+use ndarray::ArrayD;
+
+use super::{decayed_gradient, descend, zeros, Optimizer, OptimizerState};
+use crate::{Element, Tensor};
+
+/// Gradient descent, optionally with (Nesterov) momentum and weight decay.
+pub struct Sgd<A: Element> {
+    parameters: Vec<Tensor<A>>,
+    learning_rate: A,
+    pub momentum: A,
+    /// Whether to apply Nesterov's accelerated gradient on top of momentum.
+    pub nesterov: bool,
+    /// The strength of an L2 penalty on the parameters.
+    pub weight_decay: A,
+    steps: usize,
+    velocity: Vec<ArrayD<A>>,
+}
+
+impl<A: Element> Sgd<A> {
+    /// Creates plain gradient descent, `p -= learning_rate * gradient`.
+    pub fn new(parameters: Vec<Tensor<A>>, learning_rate: A) -> Self {
+        Sgd {
+            velocity: zeros(&parameters),
+            parameters,
+            learning_rate,
+            momentum: A::zero(),
+            nesterov: false,
+            weight_decay: A::zero(),
+            steps: 0,
+        }
+    }
+
+    /// Accumulates the gradients into a velocity that decays by `momentum`
+    /// every step.
+    pub fn with_momentum(mut self, momentum: A) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    /// Uses Nesterov momentum, which looks ahead along the velocity.
+    pub fn with_nesterov(mut self) -> Self {
+        self.nesterov = true;
+        self
+    }
+
+    /// Adds `weight_decay * p` to the gradient of each parameter `p`, an L2
+    /// penalty.
+    pub fn with_weight_decay(mut self, weight_decay: A) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+}
+
+impl<A: Element> Optimizer<A> for Sgd<A> {
+    fn parameters(&self) -> &[Tensor<A>] {
+        &self.parameters
+    }
+
+    fn learning_rate(&self) -> A {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: A) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn step(&mut self) {
+        for (parameter, velocity) in self.parameters.iter().zip(&mut self.velocity) {
+            let mut update = decayed_gradient(parameter, self.weight_decay);
+            if self.momentum != A::zero() {
+                *velocity = &*velocity * self.momentum + &update;
+                if self.nesterov {
+                    update = update + &*velocity * self.momentum;
+                } else {
+                    update = velocity.clone();
+                }
+            }
+            descend(parameter, &update, self.learning_rate);
+        }
+        self.steps += 1;
+    }
+
+    fn state(&self) -> OptimizerState<A> {
+        OptimizerState {
+            steps: self.steps,
+            learning_rate: self.learning_rate,
+            buffers: vec![("momentum".to_string(), self.velocity.clone())],
+        }
+    }
+
+    fn load_state(&mut self, mut state: OptimizerState<A>) {
+        self.velocity = state.take_buffer("momentum", &self.parameters);
+        self.steps = state.steps;
+        self.learning_rate = state.learning_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::optim::tests::{check_state_round_trip, minimise};
+
+    #[test]
+    fn first_steps() {
+        let x = Tensor::new(array![1.0, -2.0].into_dyn());
+        let mut sgd = Sgd::new(vec![x.clone()], 0.5)
+            .with_momentum(0.5)
+            .with_weight_decay(0.5);
+
+        for _ in 0..2 {
+            sgd.zero_grad();
+            x.sum(None, false).backward();
+            sgd.step();
+        }
+
+        // The gradients are 1 + 0.5 x: 1.5 and 0, then 1.125 and 0.
+        // The velocities are 1.5 and 0, then 1.875 and 0.
+        assert_eq!(x.value(), array![-0.6875, -2.0].into_dyn());
+    }
+
+    #[test]
+    fn nesterov_looks_ahead() {
+        let x = Tensor::new(array![1.0].into_dyn());
+        let mut sgd = Sgd::new(vec![x.clone()], 0.5)
+            .with_momentum(0.5)
+            .with_nesterov();
+        x.sum(None, false).backward();
+        sgd.step();
+        assert_eq!(x.value(), array![0.25].into_dyn());
+    }
+
+    #[test]
+    fn converges() {
+        assert!(minimise(|p| Sgd::new(p, 0.1), 200) < 1e-3);
+        assert!(minimise(|p| Sgd::new(p, 0.05).with_momentum(0.9), 200) < 1e-3);
+        assert!(
+            minimise(
+                |p| Sgd::new(p, 0.05).with_momentum(0.9).with_nesterov(),
+                200
+            ) < 1e-3
+        );
+    }
+
+    #[test]
+    fn state_round_trip() {
+        check_state_round_trip(|p| Sgd::new(p, 0.1).with_momentum(0.9));
+    }
+}
@@ -0,0 +1,202 @@
+//! Differentiable n-dimensional arrays backed by `ndarray`.
+
+mod conv;
+mod index;
+mod linalg;
+mod reduce;
+mod shape;
+mod softmax;
+
+use std::{
+    fmt::Debug,
+    ops::{Add, AddAssign, Div, Mul, Sub},
+};
+
+use ndarray::{ArrayD, LinalgScalar, ScalarOperand};
+use num::{traits::FloatConst, Float};
+
+use crate::{differentiable::impl_scalar_lhs, Var};
+
+pub use conv::{Conv1dOptions, Conv2dOptions};
+
+/// A differentiable n-dimensional array.
+///
+/// All the operators and elementwise functions available on scalar [`Var`]s
+/// work on whole arrays, with gradients of the same shape as the value.
+pub type Tensor<A> = Var<ArrayD<A>>;
+
+/// The element types of tensors, in practice `f32` and `f64`.
+pub trait Element: Float + FloatConst + LinalgScalar + ScalarOperand + AddAssign + Debug {}
+
+impl<A> Element for A where A: Float + FloatConst + LinalgScalar + ScalarOperand + AddAssign + Debug {}
+
+impl<A: Element> Add<A> for &Tensor<A> {
+    type Output = Tensor<A>;
+
+    fn add(self, rhs: A) -> Self::Output {
+        Var::from_op(self.value() + rhs, vec![self.clone()], |diff| {
+            vec![diff.gradient.clone()]
+        })
+    }
+}
+
+impl<A: Element> Sub<A> for &Tensor<A> {
+    type Output = Tensor<A>;
+
+    fn sub(self, rhs: A) -> Self::Output {
+        Var::from_op(self.value() - rhs, vec![self.clone()], |diff| {
+            vec![diff.gradient.clone()]
+        })
+    }
+}
+
+impl<A: Element> Mul<A> for &Tensor<A> {
+    type Output = Tensor<A>;
+
+    fn mul(self, rhs: A) -> Self::Output {
+        Var::from_op(self.value() * rhs, vec![self.clone()], move |diff| {
+            vec![&diff.gradient * rhs]
+        })
+    }
+}
+
+impl<A: Element> Div<A> for &Tensor<A> {
+    type Output = Tensor<A>;
+
+    fn div(self, rhs: A) -> Self::Output {
+        Var::from_op(self.value() / rhs, vec![self.clone()], move |diff| {
+            vec![&diff.gradient / rhs]
+        })
+    }
+}
+
+/// Implements an operator between an owned tensor and a constant scalar.
+macro_rules! forward_scalar_op {
+    ($Op:ident, $method:ident) => {
+        impl<A: Element> $Op<A> for Tensor<A> {
+            type Output = Tensor<A>;
+
+            fn $method(self, rhs: A) -> Self::Output {
+                $Op::$method(&self, rhs)
+            }
+        }
+    };
+}
+
+forward_scalar_op!(Add, add);
+forward_scalar_op!(Sub, sub);
+forward_scalar_op!(Mul, mul);
+forward_scalar_op!(Div, div);
+
+impl_scalar_lhs!(f32 => ArrayD<f32>, f64 => ArrayD<f64>);
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, ArrayD};
+
+    use super::*;
+    use crate::testing::check_tensor_gradients;
+
+    fn matrix() -> ArrayD<f64> {
+        array![[0.5, -1.5, 2.0], [1.0, 0.25, -0.75]].into_dyn()
+    }
+
+    fn other_matrix() -> ArrayD<f64> {
+        array![[1.5, 0.5, -2.5], [0.75, -1.25, 2.0]].into_dyn()
+    }
+
+    #[test]
+    fn gradient_has_the_shape_of_the_value() {
+        let x = Tensor::new(matrix());
+        assert_eq!(x.gradient(), ArrayD::zeros(vec![2, 3]));
+
+        let y = &x * &x;
+        assert_eq!(y.gradient().shape(), &[2, 3]);
+    }
+
+    #[test]
+    fn elementwise_operators() {
+        let x = Tensor::new(matrix());
+        let y = Tensor::new(other_matrix());
+        let result = &(&x * &y) + &x;
+
+        result.backward();
+
+        assert_eq!(result.value(), matrix() * other_matrix() + matrix());
+        assert_eq!(x.gradient(), other_matrix() + 1.0);
+        assert_eq!(y.gradient(), matrix());
+    }
+
+    #[test]
+    fn finite_differences_operators() {
+        let inputs = [matrix(), other_matrix()];
+        check_tensor_gradients(&inputs, |x| &x[0] + &x[1]);
+        check_tensor_gradients(&inputs, |x| &x[0] - &x[1]);
+        check_tensor_gradients(&inputs, |x| &x[0] * &x[1]);
+        check_tensor_gradients(&inputs, |x| &x[0] / &x[1]);
+        check_tensor_gradients(&inputs, |x| -&x[0]);
+    }
+
+    #[test]
+    fn finite_differences_scalar_operators() {
+        let inputs = [matrix()];
+        check_tensor_gradients(&inputs, |x| 3.0 * &x[0] - &x[0] / 2.0);
+        check_tensor_gradients(&inputs, |x| 1.0 - &x[0] + 2.0);
+        check_tensor_gradients(&inputs, |x| 3.0 / (&x[0] * 1.5));
+    }
+
+    #[test]
+    fn bias_addition_broadcasts() {
+        let x = Tensor::new(matrix());
+        let bias = Tensor::new(array![1.0, 2.0, 3.0].into_dyn());
+        let result = &x + &bias;
+
+        result.backward();
+
+        assert_eq!(result.value(), matrix() + array![1.0, 2.0, 3.0]);
+        assert_eq!(bias.gradient(), array![2.0, 2.0, 2.0].into_dyn());
+        assert_eq!(x.gradient(), ArrayD::ones(vec![2, 3]));
+    }
+
+    #[test]
+    fn finite_differences_broadcasting() {
+        let row = array![0.5, -1.25, 2.0].into_dyn();
+        let column = array![[1.5], [-0.5]].into_dyn();
+        let scalar = ndarray::arr0(0.75).into_dyn();
+
+        for (a, b) in [
+            (matrix(), row.clone()),
+            (matrix(), column.clone()),
+            (matrix(), scalar.clone()),
+            (column.clone(), row.clone()),
+        ] {
+            let inputs = [a.clone(), b.clone()];
+            check_tensor_gradients(&inputs, |x| &x[0] + &x[1]);
+            check_tensor_gradients(&inputs, |x| &x[0] - &x[1]);
+            check_tensor_gradients(&inputs, |x| &x[0] * &x[1]);
+            check_tensor_gradients(&inputs, |x| &x[0] / &x[1]);
+
+            let inputs = [b, a];
+            check_tensor_gradients(&inputs, |x| &x[0] * &x[1]);
+            check_tensor_gradients(&inputs, |x| &x[0] / &x[1]);
+        }
+    }
+
+    #[test]
+    fn finite_differences_broadcasting_constants() {
+        let inputs = [array![0.5, -1.25, 2.0].into_dyn()];
+        check_tensor_gradients(&inputs, |x| &x[0] * matrix());
+        check_tensor_gradients(&inputs, |x| &x[0] + matrix());
+        check_tensor_gradients(&inputs, |x| &x[0] - matrix());
+        check_tensor_gradients(&inputs, |x| &x[0] / other_matrix());
+    }
+
+    #[test]
+    fn finite_differences_elementwise_functions() {
+        let inputs = [matrix(), other_matrix()];
+        check_tensor_gradients(&inputs, |x| x[0].exp() * x[1].tanh());
+        check_tensor_gradients(&inputs, |x| x[0].powi(2).sqrt());
+        check_tensor_gradients(&inputs, |x| x[0].atan2(&x[1]));
+        check_tensor_gradients(&inputs, |x| x[0].gelu() + x[1].relu());
+    }
+}
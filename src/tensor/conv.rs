@@ -0,0 +1,828 @@
+//! Convolutions and pooling over images laid out as `[batch, channels, height,
+//! width]`.
+//!
+//! Convolutions are computed by unrolling the sliding windows of the input into
+//! columns (im2col) and multiplying them with the weights, so their gradients
+//! come from the matrix products and the unrolling itself.
+
+use ndarray::{Array3, Array4, ArrayD, Ix3, Ix4};
+
+use super::{Element, Tensor};
+use crate::Var;
+
+/// The geometry of a 2-dimensional sliding window.
+#[derive(Clone, Copy, Debug)]
+struct Window {
+    kernel: (usize, usize),
+    stride: (usize, usize),
+    padding: (usize, usize),
+    dilation: (usize, usize),
+}
+
+impl Window {
+    /// Returns the number of window positions along each axis of an input of
+    /// the given size.
+    fn output_size(&self, (height, width): (usize, usize)) -> (usize, usize) {
+        let positions = |size: usize,
+                         kernel: usize,
+                         stride: usize,
+                         padding: usize,
+                         dilation: usize| {
+            assert!(
+                kernel >= 1 && stride >= 1 && dilation >= 1,
+                "window kernel, stride and dilation must be at least 1, got {kernel}, {stride} and {dilation}"
+            );
+            let span = dilation * (kernel - 1) + 1;
+            assert!(
+                size + 2 * padding >= span,
+                "window of size {span} doesn't fit in an input of size {size} with padding {padding}"
+            );
+            (size + 2 * padding - span) / stride + 1
+        };
+        (
+            positions(
+                height,
+                self.kernel.0,
+                self.stride.0,
+                self.padding.0,
+                self.dilation.0,
+            ),
+            positions(
+                width,
+                self.kernel.1,
+                self.stride.1,
+                self.padding.1,
+                self.dilation.1,
+            ),
+        )
+    }
+
+    /// Calls `f` with every `(row, column, input_y, input_x)` such that element
+    /// `row` of column `column` of the unrolled input of a single channel reads
+    /// the input at `(input_y, input_x)`. Positions in the padding are skipped.
+    fn for_each_tap(
+        &self,
+        (height, width): (usize, usize),
+        mut f: impl FnMut(usize, usize, usize, usize),
+    ) {
+        let (output_height, output_width) = self.output_size((height, width));
+        let (kernel_height, kernel_width) = self.kernel;
+
+        for i in 0..kernel_height {
+            for j in 0..kernel_width {
+                let row = i * kernel_width + j;
+                for y in 0..output_height {
+                    let input_y =
+                        (y * self.stride.0 + i * self.dilation.0).checked_sub(self.padding.0);
+                    let Some(input_y) = input_y.filter(|&input_y| input_y < height) else {
+                        continue;
+                    };
+                    for x in 0..output_width {
+                        let input_x =
+                            (x * self.stride.1 + j * self.dilation.1).checked_sub(self.padding.1);
+                        if let Some(input_x) = input_x.filter(|&input_x| input_x < width) {
+                            f(row, y * output_width + x, input_y, input_x);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn as_images<'a, A>(x: &'a ArrayD<A>, op: &str) -> ndarray::ArrayView4<'a, A> {
+    x.view().into_dimensionality::<Ix4>().unwrap_or_else(|_| {
+        panic!(
+            "{op} expects a [batch, channels, height, width] tensor, got shape {:?}",
+            x.shape()
+        )
+    })
+}
+
+/// Unrolls the windows of `[batch, channels, height, width]` images into
+/// `[batch, channels * kernel_height * kernel_width, windows]` columns.
+fn im2col<A: Element>(x: &ArrayD<A>, window: Window) -> ArrayD<A> {
+    let x = as_images(x, "unfold2d");
+    let (batch, channels, height, width) = x.dim();
+    let (output_height, output_width) = window.output_size((height, width));
+    let kernel = window.kernel.0 * window.kernel.1;
+
+    let mut columns = Array3::zeros((batch, channels * kernel, output_height * output_width));
+    for b in 0..batch {
+        for c in 0..channels {
+            window.for_each_tap((height, width), |row, column, y, x_| {
+                columns[[b, c * kernel + row, column]] = x[[b, c, y, x_]];
+            });
+        }
+    }
+    columns.into_dyn()
+}
+
+/// Sums unrolled columns back into `[batch, channels, height, width]` images,
+/// the adjoint of [`im2col`].
+fn col2im<A: Element>(
+    columns: &ArrayD<A>,
+    (height, width): (usize, usize),
+    window: Window,
+) -> ArrayD<A> {
+    let columns = columns
+        .view()
+        .into_dimensionality::<Ix3>()
+        .unwrap_or_else(|_| {
+            panic!(
+                "fold2d expects a 3-dimensional tensor, got shape {:?}",
+                columns.shape()
+            )
+        });
+    let (batch, rows, _) = columns.dim();
+    let kernel = window.kernel.0 * window.kernel.1;
+    let channels = rows / kernel;
+
+    let mut images = Array4::zeros((batch, channels, height, width));
+    for b in 0..batch {
+        for c in 0..channels {
+            window.for_each_tap((height, width), |row, column, y, x| {
+                images[[b, c, y, x]] += columns[[b, c * kernel + row, column]];
+            });
+        }
+    }
+    images.into_dyn()
+}
+
+/// Options for [`Tensor::conv2d`] and [`Tensor::conv_transpose2d`], given as
+/// `(height, width)` pairs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conv2dOptions {
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    /// The number of groups the channels are split into, each convolved with
+    /// its own slice of the weights.
+    pub groups: usize,
+}
+
+impl Default for Conv2dOptions {
+    fn default() -> Self {
+        Conv2dOptions {
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+            groups: 1,
+        }
+    }
+}
+
+/// Options for [`Tensor::conv1d`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conv1dOptions {
+    pub stride: usize,
+    pub padding: usize,
+    pub dilation: usize,
+    /// The number of groups the channels are split into, each convolved with
+    /// its own slice of the weights.
+    pub groups: usize,
+}
+
+impl Default for Conv1dOptions {
+    fn default() -> Self {
+        Conv1dOptions {
+            stride: 1,
+            padding: 0,
+            dilation: 1,
+            groups: 1,
+        }
+    }
+}
+
+impl<A: Element> Tensor<A> {
+    /// Unrolls the sliding windows of `[batch, channels, height, width]` images
+    /// into a `[batch, channels * kernel_height * kernel_width, windows]`
+    /// tensor, padding the images with zeros.
+    pub fn unfold2d(
+        &self,
+        kernel: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+        dilation: (usize, usize),
+    ) -> Self {
+        let window = Window {
+            kernel,
+            stride,
+            padding,
+            dilation,
+        };
+        let value = im2col(&self.borrow().value, window);
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let x = as_images(&diff.children()[0].borrow().value, "unfold2d").dim();
+            vec![col2im(&diff.gradient, (x.2, x.3), window)]
+        })
+    }
+
+    /// Sums the columns produced by [`Tensor::unfold2d`] back into images of
+    /// the given `(height, width)`, adding up overlapping windows.
+    pub fn fold2d(
+        &self,
+        output_size: (usize, usize),
+        kernel: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+        dilation: (usize, usize),
+    ) -> Self {
+        let window = Window {
+            kernel,
+            stride,
+            padding,
+            dilation,
+        };
+        let value = col2im(&self.borrow().value, output_size, window);
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            vec![im2col(&diff.gradient, window)]
+        })
+    }
+
+    /// Convolves `[batch, in_channels, height, width]` images with a
+    /// `[out_channels, in_channels / groups, kernel_height, kernel_width]`
+    /// weight, adding an optional `[out_channels]` bias.
+    pub fn conv2d(
+        &self,
+        weight: &Tensor<A>,
+        bias: Option<&Tensor<A>>,
+        options: Conv2dOptions,
+    ) -> Self {
+        let (batch, channels, height, width) = as_images(&self.borrow().value, "conv2d").dim();
+        let (out_channels, group_channels, kernel_height, kernel_width) =
+            as_images(&weight.borrow().value, "conv2d").dim();
+        let groups = options.groups;
+        assert_eq!(
+            channels,
+            group_channels * groups,
+            "conv2d weight doesn't match the input channels"
+        );
+        assert_eq!(
+            out_channels % groups,
+            0,
+            "conv2d output channels must be divisible by groups"
+        );
+
+        let kernel = (kernel_height, kernel_width);
+        let window = Window {
+            kernel,
+            stride: options.stride,
+            padding: options.padding,
+            dilation: options.dilation,
+        };
+        let (output_height, output_width) = window.output_size((height, width));
+        let columns = self.unfold2d(kernel, options.stride, options.padding, options.dilation);
+
+        // Lay the windows of the whole batch side by side, so that each group
+        // is a single product with its weights.
+        let windows = output_height * output_width;
+        let columns = columns
+            .permute_axes(&[1, 0, 2])
+            .reshape(&[channels * kernel_height * kernel_width, batch * windows]);
+
+        let group_outputs = out_channels / groups;
+        let rows = group_channels * kernel_height * kernel_width;
+        let outputs: Vec<_> = (0..groups)
+            .map(|group| {
+                weight
+                    .narrow(0, group * group_outputs, group_outputs)
+                    .reshape(&[group_outputs, rows])
+                    .matmul(&columns.narrow(0, group * rows, rows))
+            })
+            .collect();
+
+        let output = Tensor::concat(&outputs, 0)
+            .reshape(&[out_channels, batch, windows])
+            .permute_axes(&[1, 0, 2])
+            .reshape(&[batch, out_channels, output_height, output_width]);
+        match bias {
+            Some(bias) => &output + &bias.reshape(&[1, out_channels, 1, 1]),
+            None => output,
+        }
+    }
+
+    /// Convolves `[batch, in_channels, length]` sequences with an
+    /// `[out_channels, in_channels / groups, kernel]` weight, adding an optional
+    /// `[out_channels]` bias.
+    pub fn conv1d(
+        &self,
+        weight: &Tensor<A>,
+        bias: Option<&Tensor<A>>,
+        options: Conv1dOptions,
+    ) -> Self {
+        let options = Conv2dOptions {
+            stride: (1, options.stride),
+            padding: (0, options.padding),
+            dilation: (1, options.dilation),
+            groups: options.groups,
+        };
+        self.unsqueeze(2)
+            .conv2d(&weight.unsqueeze(2), bias, options)
+            .squeeze(2)
+    }
+
+    /// Applies the transpose of [`Tensor::conv2d`] to `[batch, in_channels,
+    /// height, width]` images, with an `[in_channels, out_channels / groups,
+    /// kernel_height, kernel_width]` weight and an optional `[out_channels]`
+    /// bias. `output_padding` is added to one side of the output, to pick
+    /// between the sizes that convolve down to the input's size.
+    pub fn conv_transpose2d(
+        &self,
+        weight: &Tensor<A>,
+        bias: Option<&Tensor<A>>,
+        options: Conv2dOptions,
+        output_padding: (usize, usize),
+    ) -> Self {
+        let (batch, channels, height, width) =
+            as_images(&self.borrow().value, "conv_transpose2d").dim();
+        let (in_channels, group_outputs, kernel_height, kernel_width) =
+            as_images(&weight.borrow().value, "conv_transpose2d").dim();
+        let groups = options.groups;
+        assert_eq!(
+            channels, in_channels,
+            "conv_transpose2d weight doesn't match the input channels"
+        );
+        assert_eq!(
+            in_channels % groups,
+            0,
+            "conv_transpose2d input channels must be divisible by groups"
+        );
+
+        let output_size = |size: usize, kernel: usize, axis: usize| {
+            let [stride, padding, dilation, output_padding] = [
+                [options.stride.0, options.stride.1],
+                [options.padding.0, options.padding.1],
+                [options.dilation.0, options.dilation.1],
+                [output_padding.0, output_padding.1],
+            ]
+            .map(|pair| pair[axis]);
+            assert!(
+                output_padding < stride.max(dilation),
+                "conv_transpose2d output padding must be smaller than the stride or the dilation"
+            );
+            let size = (size - 1) * stride + dilation * (kernel - 1) + output_padding + 1;
+            assert!(
+                size > 2 * padding,
+                "conv_transpose2d padding {padding} leaves no output from a size of {size}"
+            );
+            size - 2 * padding
+        };
+        let output_height = output_size(height, kernel_height, 0);
+        let output_width = output_size(width, kernel_width, 1);
+
+        // Lay the pixels of the whole batch side by side, so that each group is
+        // a single product with its weights.
+        let pixels = height * width;
+        let input = self
+            .reshape(&[batch, channels, pixels])
+            .permute_axes(&[1, 0, 2])
+            .reshape(&[channels, batch * pixels]);
+
+        let group_channels = in_channels / groups;
+        let rows = group_outputs * kernel_height * kernel_width;
+        let columns: Vec<_> = (0..groups)
+            .map(|group| {
+                weight
+                    .narrow(0, group * group_channels, group_channels)
+                    .reshape(&[group_channels, rows])
+                    .transpose(0, 1)
+                    .matmul(&input.narrow(0, group * group_channels, group_channels))
+            })
+            .collect();
+
+        let output = Tensor::concat(&columns, 0)
+            .reshape(&[groups * rows, batch, pixels])
+            .permute_axes(&[1, 0, 2])
+            .fold2d(
+                (output_height, output_width),
+                (kernel_height, kernel_width),
+                options.stride,
+                options.padding,
+                options.dilation,
+            );
+        match bias {
+            Some(bias) => &output + &bias.reshape(&[1, group_outputs * groups, 1, 1]),
+            None => output,
+        }
+    }
+
+    /// Takes the maximum of each window of `[batch, channels, height, width]`
+    /// images. Padding never wins the maximum, and may be at most half the
+    /// kernel so that every window holds part of the image.
+    pub fn max_pool2d(
+        &self,
+        kernel: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+    ) -> Self {
+        assert!(
+            padding.0 <= kernel.0 / 2 && padding.1 <= kernel.1 / 2,
+            "max_pool2d padding {padding:?} must be at most half the kernel {kernel:?}"
+        );
+        let window = Window {
+            kernel,
+            stride,
+            padding,
+            dilation: (1, 1),
+        };
+        let (value, winners) = {
+            let node = self.borrow();
+            let x = as_images(&node.value, "max_pool2d");
+            let (batch, channels, height, width) = x.dim();
+            let (output_height, output_width) = window.output_size((height, width));
+
+            let mut value = Array4::from_elem(
+                (batch, channels, output_height, output_width),
+                A::neg_infinity(),
+            );
+            // The input position each output was read from.
+            let mut winners =
+                Array4::from_elem((batch, channels, output_height, output_width), (0, 0));
+            for b in 0..batch {
+                for c in 0..channels {
+                    window.for_each_tap((height, width), |_, column, y, x_| {
+                        let output = [b, c, column / output_width, column % output_width];
+                        if x[[b, c, y, x_]] > value[output] {
+                            value[output] = x[[b, c, y, x_]];
+                            winners[output] = (y, x_);
+                        }
+                    });
+                }
+            }
+            (value.into_dyn(), winners)
+        };
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let mut gradient =
+                Array4::zeros(as_images(&diff.children()[0].borrow().value, "max_pool2d").dim());
+            for ((b, c, y, x), &(input_y, input_x)) in winners.indexed_iter() {
+                gradient[[b, c, input_y, input_x]] += diff.gradient[[b, c, y, x]];
+            }
+            vec![gradient.into_dyn()]
+        })
+    }
+
+    /// Averages each window of `[batch, channels, height, width]` images,
+    /// counting the zero padding as part of the window.
+    pub fn avg_pool2d(
+        &self,
+        kernel: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+    ) -> Self {
+        let (batch, channels, height, width) = as_images(&self.borrow().value, "avg_pool2d").dim();
+        let window = Window {
+            kernel,
+            stride,
+            padding,
+            dilation: (1, 1),
+        };
+        let (output_height, output_width) = window.output_size((height, width));
+
+        self.unfold2d(kernel, stride, padding, (1, 1))
+            .reshape(&[
+                batch,
+                channels,
+                kernel.0 * kernel.1,
+                output_height * output_width,
+            ])
+            .mean(Some(2), false)
+            .reshape(&[batch, channels, output_height, output_width])
+    }
+
+    /// Averages `[batch, channels, height, width]` images down to the given
+    /// `(height, width)`, with windows spread as evenly as possible.
+    pub fn adaptive_avg_pool2d(&self, output_size: (usize, usize)) -> Self {
+        let (batch, channels, height, width) =
+            as_images(&self.borrow().value, "adaptive_avg_pool2d").dim();
+        let (output_height, output_width) = output_size;
+        assert!(
+            output_height >= 1 && output_width >= 1,
+            "adaptive_avg_pool2d output size must be at least 1, got {output_size:?}"
+        );
+        // The input range averaged into output `i` along an axis.
+        let bounds = |i: usize, input: usize, output: usize| {
+            (i * input / output, ((i + 1) * input).div_ceil(output))
+        };
+
+        let mut value = Array4::zeros((batch, channels, output_height, output_width));
+        {
+            let node = self.borrow();
+            let x = as_images(&node.value, "adaptive_avg_pool2d");
+            for ((b, c, i, j), output) in value.indexed_iter_mut() {
+                let (top, bottom) = bounds(i, height, output_height);
+                let (left, right) = bounds(j, width, output_width);
+                let window = x.slice(ndarray::s![b, c, top..bottom, left..right]);
+                *output = window.sum() / A::from(window.len()).unwrap();
+            }
+        }
+
+        Var::from_op(value.into_dyn(), vec![self.clone()], move |diff| {
+            let mut gradient = Array4::zeros((batch, channels, height, width));
+            for b in 0..batch {
+                for c in 0..channels {
+                    for i in 0..output_height {
+                        for j in 0..output_width {
+                            let (top, bottom) = bounds(i, height, output_height);
+                            let (left, right) = bounds(j, width, output_width);
+                            let mut window =
+                                gradient.slice_mut(ndarray::s![b, c, top..bottom, left..right]);
+                            let share =
+                                diff.gradient[[b, c, i, j]] / A::from(window.len()).unwrap();
+                            window += share;
+                        }
+                    }
+                }
+            }
+            vec![gradient.into_dyn()]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, Array, Axis};
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence, weighted};
+
+    /// Computes a convolution directly from its definition.
+    fn naive_conv2d(x: &ArrayD<f64>, weight: &ArrayD<f64>, options: Conv2dOptions) -> ArrayD<f64> {
+        let x = as_images(x, "test");
+        let weight = as_images(weight, "test");
+        let (batch, _, height, width) = x.dim();
+        let (out_channels, group_channels, kernel_height, kernel_width) = weight.dim();
+        let group_outputs = out_channels / options.groups;
+        let window = Window {
+            kernel: (kernel_height, kernel_width),
+            stride: options.stride,
+            padding: options.padding,
+            dilation: options.dilation,
+        };
+        let (output_height, output_width) = window.output_size((height, width));
+
+        let mut output = Array4::zeros((batch, out_channels, output_height, output_width));
+        for ((b, o, y, x_), output) in output.indexed_iter_mut() {
+            let group = o / group_outputs;
+            for c in 0..group_channels {
+                for i in 0..kernel_height {
+                    for j in 0..kernel_width {
+                        let input_y = (y * options.stride.0 + i * options.dilation.0) as isize
+                            - options.padding.0 as isize;
+                        let input_x = (x_ * options.stride.1 + j * options.dilation.1) as isize
+                            - options.padding.1 as isize;
+                        if input_y >= 0
+                            && input_x >= 0
+                            && (input_y as usize) < height
+                            && (input_x as usize) < width
+                        {
+                            *output += weight[[o, c, i, j]]
+                                * x[[
+                                    b,
+                                    group * group_channels + c,
+                                    input_y as usize,
+                                    input_x as usize,
+                                ]];
+                        }
+                    }
+                }
+            }
+        }
+        output.into_dyn()
+    }
+
+    fn option_sets() -> Vec<Conv2dOptions> {
+        vec![
+            Conv2dOptions::default(),
+            Conv2dOptions {
+                stride: (2, 1),
+                padding: (1, 2),
+                ..Default::default()
+            },
+            Conv2dOptions {
+                dilation: (2, 2),
+                padding: (1, 1),
+                ..Default::default()
+            },
+            Conv2dOptions {
+                groups: 2,
+                stride: (1, 2),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn conv2d_matches_definition() {
+        let x = sequence(&[2, 4, 5, 6]);
+        for options in option_sets() {
+            let weight = sequence(&[4, 4 / options.groups, 3, 2]).mapv(f64::sin);
+            let bias = Array::linspace(-1.0, 1.0, 4).into_dyn();
+            let output = Tensor::new(x.clone())
+                .conv2d(
+                    &Tensor::new(weight.clone()),
+                    Some(&Tensor::new(bias.clone())),
+                    options,
+                )
+                .value();
+
+            let expected =
+                naive_conv2d(&x, &weight, options) + bias.insert_axis(Axis(1)).insert_axis(Axis(2));
+            assert_eq!(output.shape(), expected.shape());
+            assert!(
+                (output - expected).iter().all(|d| d.abs() < 1e-12),
+                "{options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn finite_differences_conv2d() {
+        for options in option_sets() {
+            let inputs = [
+                sequence(&[2, 4, 4, 5]),
+                sequence(&[2, 4 / options.groups, 2, 3]).mapv(f64::cos),
+                sequence(&[2]),
+            ];
+            check_tensor_gradients(&inputs, |x| {
+                weighted(&x[0].conv2d(&x[1], Some(&x[2]), options))
+            });
+        }
+    }
+
+    #[test]
+    fn finite_differences_conv1d() {
+        let options = Conv1dOptions {
+            stride: 2,
+            padding: 1,
+            dilation: 2,
+            groups: 1,
+        };
+        let inputs = [
+            sequence(&[2, 3, 9]),
+            sequence(&[2, 3, 3]).mapv(f64::cos),
+            sequence(&[2]),
+        ];
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&x[0].conv1d(&x[1], Some(&x[2]), options))
+        });
+        assert_eq!(
+            Tensor::new(inputs[0].clone())
+                .conv1d(&Tensor::new(inputs[1].clone()), None, options)
+                .value()
+                .shape(),
+            &[2, 2, 4]
+        );
+    }
+
+    #[test]
+    fn conv_transpose2d_is_the_adjoint_of_conv2d() {
+        for options in option_sets() {
+            let x = sequence(&[2, 4, 5, 6]);
+            let weight = sequence(&[4, 4 / options.groups, 3, 2]).mapv(f64::sin);
+            let convolved =
+                Tensor::new(x.clone()).conv2d(&Tensor::new(weight.clone()), None, options);
+            let y = convolved.value().mapv(f64::cos);
+
+            // Pick the output padding that recovers the shape of `x`.
+            let (height, width) = (y.shape()[2], y.shape()[3]);
+            let output_padding = (
+                (5 + 2 * options.padding.0 - options.dilation.0 * 2 - 1)
+                    - (height - 1) * options.stride.0,
+                (6 + 2 * options.padding.1 - options.dilation.1 - 1)
+                    - (width - 1) * options.stride.1,
+            );
+            let transposed = Tensor::new(y.clone())
+                .conv_transpose2d(&Tensor::new(weight), None, options, output_padding)
+                .value();
+
+            assert_eq!(transposed.shape(), x.shape());
+            let forward = (convolved.value() * y).sum();
+            let backward = (x * transposed).sum();
+            assert!((forward - backward).abs() < 1e-9, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn finite_differences_conv_transpose2d() {
+        for options in option_sets() {
+            let inputs = [
+                sequence(&[2, 4, 3, 3]),
+                sequence(&[4, 4 / options.groups, 2, 3]).mapv(f64::cos),
+                sequence(&[4]),
+            ];
+            check_tensor_gradients(&inputs, |x| {
+                weighted(&x[0].conv_transpose2d(&x[1], Some(&x[2]), options, (0, 0)))
+            });
+        }
+    }
+
+    #[test]
+    fn pooling_values() {
+        let x = Tensor::new(sequence(&[1, 1, 4, 4]).mapv(|x| (3.0 * x).sin()));
+        let value = x.value();
+
+        let max = x.max_pool2d((2, 2), (2, 2), (0, 0)).value();
+        let avg = x.avg_pool2d((2, 2), (2, 2), (0, 0)).value();
+        let adaptive = x.adaptive_avg_pool2d((2, 2)).value();
+        for i in 0..2 {
+            for j in 0..2 {
+                let window = value.slice(ndarray::s![0, 0, 2 * i..2 * i + 2, 2 * j..2 * j + 2]);
+                assert_eq!(
+                    max[[0, 0, i, j]],
+                    window.fold(f64::NEG_INFINITY, |m, &x| m.max(x))
+                );
+                assert!((avg[[0, 0, i, j]] - window.mean().unwrap()).abs() < 1e-12);
+                assert!((adaptive[[0, 0, i, j]] - window.mean().unwrap()).abs() < 1e-12);
+            }
+        }
+
+        let negative = Tensor::new(-ArrayD::<f64>::ones(vec![1, 1, 2, 2]));
+        assert_eq!(
+            negative.max_pool2d((2, 2), (1, 1), (1, 1)).value(),
+            -ArrayD::ones(vec![1, 1, 3, 3])
+        );
+        assert_eq!(x.adaptive_avg_pool2d((3, 3)).value().shape(), &[1, 1, 3, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_pool2d padding (1, 1) must be at most half the kernel (1, 1)")]
+    fn max_pool2d_padding_is_limited_by_the_kernel() {
+        Tensor::new(sequence(&[1, 1, 2, 2])).max_pool2d((1, 1), (1, 1), (1, 1));
+    }
+
+    #[test]
+    fn max_pool2d_gradients_stay_in_the_image() {
+        let x = Tensor::new(sequence(&[1, 1, 2, 2]));
+        x.max_pool2d((2, 2), (1, 1), (1, 1)).backward();
+
+        // Only the largest element wins, in each of the nine windows.
+        let expected = array![[1.0, 2.0], [2.0, 4.0]]
+            .into_shape((1, 1, 2, 2))
+            .unwrap();
+        assert_eq!(x.gradient(), expected.into_dyn());
+    }
+
+    #[test]
+    #[should_panic(expected = "conv_transpose2d padding 3 leaves no output from a size of 4")]
+    fn conv_transpose2d_padding_is_limited() {
+        let x = Tensor::new(sequence(&[1, 1, 2, 2]));
+        let weight = Tensor::new(sequence(&[1, 1, 3, 3]));
+        let options = Conv2dOptions {
+            padding: (3, 0),
+            ..Default::default()
+        };
+        x.conv_transpose2d(&weight, None, options, (0, 0));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "conv_transpose2d output padding must be smaller than the stride or the dilation"
+    )]
+    fn conv_transpose2d_output_padding_is_limited() {
+        let x = Tensor::new(sequence(&[1, 1, 2, 2]));
+        let weight = Tensor::new(sequence(&[1, 1, 3, 3]));
+        x.conv_transpose2d(&weight, None, Conv2dOptions::default(), (1, 0));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "window kernel, stride and dilation must be at least 1, got 2, 0 and 1"
+    )]
+    fn windows_need_a_stride() {
+        Tensor::new(sequence(&[1, 1, 4, 4])).avg_pool2d((2, 2), (0, 0), (0, 0));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "window kernel, stride and dilation must be at least 1, got 0, 1 and 1"
+    )]
+    fn windows_need_a_kernel() {
+        Tensor::new(sequence(&[1, 1, 4, 4])).max_pool2d((0, 0), (1, 1), (0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "adaptive_avg_pool2d output size must be at least 1, got (0, 2)")]
+    fn adaptive_pooling_needs_an_output() {
+        Tensor::new(sequence(&[1, 1, 4, 4])).adaptive_avg_pool2d((0, 2));
+    }
+
+    #[test]
+    fn finite_differences_pooling() {
+        let inputs = [sequence(&[2, 2, 5, 5]).mapv(|x| (3.0 * x).sin())];
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&x[0].max_pool2d((2, 2), (2, 2), (1, 1)))
+        });
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&x[0].max_pool2d((3, 2), (1, 2), (0, 0)))
+        });
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&x[0].avg_pool2d((2, 2), (2, 2), (1, 1)))
+        });
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].adaptive_avg_pool2d((2, 3))));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].adaptive_avg_pool2d((1, 1))));
+    }
+}
@@ -0,0 +1,283 @@
+//! Indexing, slicing and masking, with gradients routed back to the elements
+//! that were read.
+
+use ndarray::{Array1, ArrayD, Axis, IxDyn, SliceArg, Zip};
+
+use super::{Element, Tensor};
+use crate::{Value, Var};
+
+/// Reads `x` at the positions given by `index` along `axis`, as in
+/// [`Tensor::gather`].
+fn gather<A: Element>(x: &ArrayD<A>, axis: usize, index: &ArrayD<usize>) -> ArrayD<A> {
+    assert_eq!(
+        x.ndim(),
+        index.ndim(),
+        "gather index must have the same number of axes as the input"
+    );
+
+    let mut result = ArrayD::zeros(index.raw_dim());
+    for (mut position, &i) in index.indexed_iter() {
+        let output = &mut result[&position];
+        position[axis] = i;
+        *output = x[&position];
+    }
+    result
+}
+
+/// Adds `source` into `x` at the positions given by `index` along `axis`, as
+/// in [`Tensor::scatter_add`].
+fn scatter_add<A: Element>(
+    x: &mut ArrayD<A>,
+    axis: usize,
+    index: &ArrayD<usize>,
+    source: &ArrayD<A>,
+) {
+    for (mut position, &i) in index.indexed_iter() {
+        let value = source[&position];
+        position[axis] = i;
+        x[&position] += value;
+    }
+}
+
+impl<A: Element> Tensor<A> {
+    /// Takes a slice, with the same semantics as [`ndarray::s!`].
+    pub fn slice<I>(&self, info: I) -> Self
+    where
+        I: SliceArg<IxDyn> + Clone + 'static,
+    {
+        let value = self
+            .borrow()
+            .value
+            .slice(info.clone())
+            .to_owned()
+            .into_dyn();
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let mut gradient = diff.children()[0].borrow().value.zeros_like();
+            gradient.slice_mut(info.clone()).assign(&diff.gradient);
+            vec![gradient]
+        })
+    }
+
+    /// Takes the sub-arrays at `indices` along `axis`, in order. Indices may
+    /// repeat, in which case their gradients add up.
+    pub fn index_select(&self, axis: usize, indices: &[usize]) -> Self {
+        let value = self.borrow().value.select(Axis(axis), indices);
+        let indices = indices.to_vec();
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let mut gradient = diff.children()[0].borrow().value.zeros_like();
+            for (i, &index) in indices.iter().enumerate() {
+                let mut target = gradient.index_axis_mut(Axis(axis), index);
+                target += &diff.gradient.index_axis(Axis(axis), i);
+            }
+            vec![gradient]
+        })
+    }
+
+    /// Reads elements along `axis` at the positions given by `index`, which has
+    /// the same number of axes as the input and the shape of the result. For a
+    /// 3-dimensional tensor and `axis == 1`, `result[i][j][k]` is
+    /// `self[i][index[i][j][k]][k]`.
+    pub fn gather(&self, axis: usize, index: &ArrayD<usize>) -> Self {
+        let value = gather(&self.borrow().value, axis, index);
+        let index = index.clone();
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let mut gradient = diff.children()[0].borrow().value.zeros_like();
+            scatter_add(&mut gradient, axis, &index, &diff.gradient);
+            vec![gradient]
+        })
+    }
+
+    /// Adds the elements of `source` into a copy of the tensor at the positions
+    /// given by `index` along `axis`, the inverse of [`Tensor::gather`]. For a
+    /// 3-dimensional tensor and `axis == 1`, `source[i][j][k]` is added to
+    /// `result[i][index[i][j][k]][k]`.
+    pub fn scatter_add(&self, axis: usize, index: &ArrayD<usize>, source: &Tensor<A>) -> Self {
+        let mut value = self.value();
+        scatter_add(&mut value, axis, index, &source.borrow().value);
+        let index = index.clone();
+
+        Var::from_op(value, vec![self.clone(), source.clone()], move |diff| {
+            vec![diff.gradient.clone(), gather(&diff.gradient, axis, &index)]
+        })
+    }
+
+    /// Takes the elements where `mask` is set, in row-major order, as a
+    /// 1-dimensional tensor.
+    pub fn masked_select(&self, mask: &ArrayD<bool>) -> Self {
+        let selected = {
+            let x = &self.borrow().value;
+            assert_eq!(
+                x.shape(),
+                mask.shape(),
+                "mask must have the shape of the input"
+            );
+            Zip::from(x)
+                .and(mask)
+                .fold(Vec::new(), |mut selected, &x, &keep| {
+                    if keep {
+                        selected.push(x);
+                    }
+                    selected
+                })
+        };
+        let value = Array1::from(selected).into_dyn();
+        let mask = mask.clone();
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let mut gradient = diff.children()[0].borrow().value.zeros_like();
+            let mut selected = diff.gradient.iter();
+            Zip::from(&mut gradient)
+                .and(&mask)
+                .for_each(|gradient, &keep| {
+                    if keep {
+                        *gradient = *selected.next().unwrap();
+                    }
+                });
+            vec![gradient]
+        })
+    }
+
+    /// Replaces the elements where `mask` is set with `value`.
+    pub fn masked_fill(&self, mask: &ArrayD<bool>, value: A) -> Self {
+        let mut result = self.value();
+        assert_eq!(
+            result.shape(),
+            mask.shape(),
+            "mask must have the shape of the input"
+        );
+        Zip::from(&mut result).and(mask).for_each(|x, &fill| {
+            if fill {
+                *x = value;
+            }
+        });
+        let mask = mask.clone();
+
+        Var::from_op(result, vec![self.clone()], move |diff| {
+            let mut gradient = diff.gradient.clone();
+            Zip::from(&mut gradient)
+                .and(&mask)
+                .for_each(|gradient, &fill| {
+                    if fill {
+                        *gradient = A::zero();
+                    }
+                });
+            vec![gradient]
+        })
+    }
+
+    /// Picks elements from `a` where `condition` is set and from `b` elsewhere.
+    /// All three must have the same shape.
+    pub fn where_(condition: &ArrayD<bool>, a: &Tensor<A>, b: &Tensor<A>) -> Self {
+        let value = Zip::from(condition)
+            .and(&a.borrow().value)
+            .and(&b.borrow().value)
+            .map_collect(|&condition, &a, &b| if condition { a } else { b });
+        let condition = condition.clone();
+
+        Var::from_op(value, vec![a.clone(), b.clone()], move |diff| {
+            let route = |to_a: bool| {
+                Zip::from(&condition)
+                    .and(&diff.gradient)
+                    .map_collect(|&condition, &gradient| {
+                        if condition == to_a {
+                            gradient
+                        } else {
+                            A::zero()
+                        }
+                    })
+            };
+            vec![route(true), route(false)]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, s, Array};
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence, weighted};
+
+    #[test]
+    fn values() {
+        let x = Tensor::new(array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn());
+        assert_eq!(
+            x.slice(s![.., 1..]).value(),
+            array![[2.0, 3.0], [5.0, 6.0]].into_dyn()
+        );
+        assert_eq!(x.slice(s![1, ..;2]).value(), array![4.0, 6.0].into_dyn());
+        assert_eq!(
+            x.index_select(1, &[2, 0, 2]).value(),
+            array![[3.0, 1.0, 3.0], [6.0, 4.0, 6.0]].into_dyn()
+        );
+        assert_eq!(
+            x.gather(1, &array![[2, 0], [1, 1]].into_dyn()).value(),
+            array![[3.0, 1.0], [5.0, 5.0]].into_dyn()
+        );
+
+        let mask = array![[true, false, true], [false, false, true]].into_dyn();
+        assert_eq!(
+            x.masked_select(&mask).value(),
+            array![1.0, 3.0, 6.0].into_dyn()
+        );
+        assert_eq!(
+            x.masked_fill(&mask, 0.0).value(),
+            array![[0.0, 2.0, 0.0], [4.0, 5.0, 0.0]].into_dyn()
+        );
+
+        let y = Tensor::new(Array::zeros(vec![2, 3]));
+        assert_eq!(
+            Tensor::where_(&mask, &x, &y).value(),
+            array![[1.0, 0.0, 3.0], [0.0, 0.0, 6.0]].into_dyn()
+        );
+    }
+
+    #[test]
+    fn embedding_lookup_accumulates_repeated_rows() {
+        let table = Tensor::new(sequence(&[4, 2]));
+        table.index_select(0, &[1, 3, 1]).backward();
+
+        assert_eq!(
+            table.gradient(),
+            array![[0.0, 0.0], [2.0, 2.0], [0.0, 0.0], [1.0, 1.0]].into_dyn()
+        );
+    }
+
+    #[test]
+    fn finite_differences_slice_and_select() {
+        let inputs = [sequence(&[3, 4])];
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].slice(s![1.., ..;2])));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].slice(s![2, 1..3])));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].index_select(0, &[2, 0, 2])));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].index_select(1, &[3, 1])));
+    }
+
+    #[test]
+    fn finite_differences_gather_and_scatter() {
+        let index = array![[2, 0, 1, 2], [0, 0, 2, 1]].into_dyn();
+        check_tensor_gradients(&[sequence(&[3, 4])], |x| weighted(&x[0].gather(0, &index)));
+        check_tensor_gradients(
+            &[sequence(&[3, 4]), sequence(&[2, 4]).mapv(f64::cos)],
+            |x| weighted(&x[0].scatter_add(0, &index, &x[1])),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "mask must have the shape of the input")]
+    fn masked_fill_checks_the_mask() {
+        let mask = array![true, false].into_dyn();
+        Tensor::new(sequence(&[2, 3])).masked_fill(&mask, 0.0);
+    }
+
+    #[test]
+    fn finite_differences_masks() {
+        let mask = array![[true, false, true], [false, false, true]].into_dyn();
+        let inputs = [sequence(&[2, 3]), sequence(&[2, 3]).mapv(f64::sin)];
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].masked_select(&mask)));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].masked_fill(&mask, -1e9)).exp());
+        check_tensor_gradients(&inputs, |x| weighted(&Tensor::where_(&mask, &x[0], &x[1])));
+    }
+}
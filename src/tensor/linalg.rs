@@ -0,0 +1,212 @@
+//! Matrix products.
+
+use ndarray::{Array3, ArrayD, ArrayView1, ArrayView2, ArrayView3, Axis, Ix1, Ix2, Ix3};
+
+use super::{Element, Tensor};
+use crate::Var;
+
+fn as_vector<'a, A>(array: &'a ArrayD<A>, op: &str) -> ArrayView1<'a, A> {
+    array
+        .view()
+        .into_dimensionality::<Ix1>()
+        .unwrap_or_else(|_| {
+            panic!(
+                "{op} expects 1-dimensional tensors, got shape {:?}",
+                array.shape()
+            )
+        })
+}
+
+fn as_matrix<'a, A>(array: &'a ArrayD<A>, op: &str) -> ArrayView2<'a, A> {
+    array
+        .view()
+        .into_dimensionality::<Ix2>()
+        .unwrap_or_else(|_| {
+            panic!(
+                "{op} expects 2-dimensional tensors, got shape {:?}",
+                array.shape()
+            )
+        })
+}
+
+fn as_batch<'a, A>(array: &'a ArrayD<A>, op: &str) -> ArrayView3<'a, A> {
+    array
+        .view()
+        .into_dimensionality::<Ix3>()
+        .unwrap_or_else(|_| {
+            panic!(
+                "{op} expects 3-dimensional tensors, got shape {:?}",
+                array.shape()
+            )
+        })
+}
+
+/// Multiplies every pair of matrices in two batches.
+fn batch_dot<A: Element>(a: ArrayView3<A>, b: ArrayView3<A>) -> Array3<A> {
+    assert_eq!(
+        a.len_of(Axis(0)),
+        b.len_of(Axis(0)),
+        "bmm expects equal batch sizes"
+    );
+
+    let mut result = Array3::zeros((a.len_of(Axis(0)), a.len_of(Axis(1)), b.len_of(Axis(2))));
+    for ((a, b), mut c) in a
+        .outer_iter()
+        .zip(b.outer_iter())
+        .zip(result.outer_iter_mut())
+    {
+        c.assign(&a.dot(&b));
+    }
+
+    result
+}
+
+impl<A: Element> Tensor<A> {
+    /// Multiplies two matrices of shapes `[m, k]` and `[k, n]`.
+    pub fn matmul(&self, other: &Tensor<A>) -> Self {
+        let value = {
+            let (a, b) = (self.borrow(), other.borrow());
+            as_matrix(&a.value, "matmul").dot(&as_matrix(&b.value, "matmul"))
+        };
+
+        Var::from_op(
+            value.into_dyn(),
+            vec![self.clone(), other.clone()],
+            |diff| {
+                let gradient = as_matrix(&diff.gradient, "matmul");
+                let a = &diff.children()[0].borrow().value;
+                let b = &diff.children()[1].borrow().value;
+                vec![
+                    gradient.dot(&as_matrix(b, "matmul").t()).into_dyn(),
+                    as_matrix(a, "matmul").t().dot(&gradient).into_dyn(),
+                ]
+            },
+        )
+    }
+
+    /// Computes the inner product of two vectors, as a 0-dimensional tensor.
+    pub fn dot(&self, other: &Tensor<A>) -> Self {
+        let value = {
+            let (a, b) = (self.borrow(), other.borrow());
+            as_vector(&a.value, "dot").dot(&as_vector(&b.value, "dot"))
+        };
+
+        Var::from_op(
+            ndarray::arr0(value).into_dyn(),
+            vec![self.clone(), other.clone()],
+            |diff| {
+                let gradient = diff.gradient.first().copied().unwrap();
+                vec![
+                    &diff.children()[1].borrow().value * gradient,
+                    &diff.children()[0].borrow().value * gradient,
+                ]
+            },
+        )
+    }
+
+    /// Computes the outer product of vectors of lengths `m` and `n`, as an
+    /// `[m, n]` matrix.
+    pub fn outer(&self, other: &Tensor<A>) -> Self {
+        let value = {
+            let (a, b) = (self.borrow(), other.borrow());
+            let a = as_vector(&a.value, "outer").insert_axis(Axis(1));
+            let b = as_vector(&b.value, "outer").insert_axis(Axis(0));
+            a.dot(&b)
+        };
+
+        Var::from_op(
+            value.into_dyn(),
+            vec![self.clone(), other.clone()],
+            |diff| {
+                let gradient = as_matrix(&diff.gradient, "outer");
+                let a = &diff.children()[0].borrow().value;
+                let b = &diff.children()[1].borrow().value;
+                vec![
+                    gradient.dot(&as_vector(b, "outer")).into_dyn(),
+                    gradient.t().dot(&as_vector(a, "outer")).into_dyn(),
+                ]
+            },
+        )
+    }
+
+    /// Multiplies two batches of matrices of shapes `[batch, m, k]` and
+    /// `[batch, k, n]`, pairwise.
+    pub fn bmm(&self, other: &Tensor<A>) -> Self {
+        let value = {
+            let (a, b) = (self.borrow(), other.borrow());
+            batch_dot(as_batch(&a.value, "bmm"), as_batch(&b.value, "bmm"))
+        };
+
+        Var::from_op(
+            value.into_dyn(),
+            vec![self.clone(), other.clone()],
+            |diff| {
+                let gradient = as_batch(&diff.gradient, "bmm");
+                let a = &diff.children()[0].borrow().value;
+                let b = &diff.children()[1].borrow().value;
+                let a = as_batch(a, "bmm");
+                let b = as_batch(b, "bmm");
+                vec![
+                    batch_dot(gradient, b.permuted_axes([0, 2, 1])).into_dyn(),
+                    batch_dot(a.permuted_axes([0, 2, 1]), gradient).into_dyn(),
+                ]
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence};
+
+    #[test]
+    fn matmul_values() {
+        let a = Tensor::new(array![[1.0, 2.0], [3.0, 4.0]].into_dyn());
+        let b = Tensor::new(array![[5.0, 6.0], [7.0, 8.0]].into_dyn());
+        let c = a.matmul(&b);
+
+        c.backward();
+
+        assert_eq!(c.value(), array![[19.0, 22.0], [43.0, 50.0]].into_dyn());
+        assert_eq!(a.gradient(), array![[11.0, 15.0], [11.0, 15.0]].into_dyn());
+        assert_eq!(b.gradient(), array![[4.0, 4.0], [6.0, 6.0]].into_dyn());
+    }
+
+    #[test]
+    fn finite_differences_matmul() {
+        check_tensor_gradients(&[sequence(&[2, 3]), sequence(&[3, 4])], |x| {
+            x[0].matmul(&x[1]).tanh()
+        });
+    }
+
+    #[test]
+    fn finite_differences_dot() {
+        check_tensor_gradients(&[sequence(&[4]), sequence(&[4]).mapv(f64::cos)], |x| {
+            x[0].dot(&x[1]).sin()
+        });
+    }
+
+    #[test]
+    fn finite_differences_outer() {
+        check_tensor_gradients(&[sequence(&[3]), sequence(&[2])], |x| {
+            x[0].outer(&x[1]).tanh()
+        });
+    }
+
+    #[test]
+    fn finite_differences_bmm() {
+        check_tensor_gradients(&[sequence(&[2, 3, 2]), sequence(&[2, 2, 4])], |x| {
+            x[0].bmm(&x[1]).tanh()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "matmul expects 2-dimensional tensors")]
+    fn matmul_rejects_vectors() {
+        let a = Tensor::new(array![1.0, 2.0].into_dyn());
+        a.matmul(&a);
+    }
+}
@@ -0,0 +1,369 @@
+//! Reductions along an axis or over all elements.
+//!
+//! Every reduction takes an `axis`, where `None` reduces all the elements, and
+//! a `keepdims` flag that keeps the reduced axes with a length of one.
+
+use ndarray::{arr0, Array1, ArrayD, ArrayView1, ArrayViewMut1, Axis};
+
+use super::{Element, Tensor};
+use crate::Var;
+
+/// Reduces every lane along `axis`, or all the elements, with `f`.
+fn reduce<A: Element>(
+    x: &ArrayD<A>,
+    axis: Option<usize>,
+    keepdims: bool,
+    f: impl Fn(ArrayView1<A>) -> A,
+) -> ArrayD<A> {
+    match axis {
+        Some(axis) => {
+            let reduced = x.map_axis(Axis(axis), f);
+            if keepdims {
+                reduced.insert_axis(Axis(axis))
+            } else {
+                reduced
+            }
+        }
+        None => {
+            let flat: Array1<A> = x.iter().copied().collect();
+            let value = f(flat.view());
+            if keepdims {
+                ArrayD::from_elem(vec![1; x.ndim()], value)
+            } else {
+                arr0(value).into_dyn()
+            }
+        }
+    }
+}
+
+/// Builds an array of the shape of `x` by letting `f` fill in each lane along
+/// `axis` from the matching lane of `x`. With no axis, all the elements form a
+/// single lane.
+fn map_lanes<A: Element>(
+    x: &ArrayD<A>,
+    axis: Option<usize>,
+    f: impl Fn(ArrayView1<A>, ArrayViewMut1<A>),
+) -> ArrayD<A> {
+    match axis {
+        Some(axis) => {
+            let mut result = ArrayD::zeros(x.raw_dim());
+            for (lane, output) in x
+                .lanes(Axis(axis))
+                .into_iter()
+                .zip(result.lanes_mut(Axis(axis)))
+            {
+                f(lane, output);
+            }
+            result
+        }
+        None => {
+            let flat: Array1<A> = x.iter().copied().collect();
+            let mut result = Array1::zeros(flat.len());
+            f(flat.view(), result.view_mut());
+            ArrayD::from_shape_vec(x.raw_dim(), result.to_vec()).unwrap()
+        }
+    }
+}
+
+/// Broadcasts the gradient of a reduction back to the shape of its input.
+fn expand<A: Element>(
+    gradient: &ArrayD<A>,
+    shape: &[usize],
+    axis: Option<usize>,
+    keepdims: bool,
+) -> ArrayD<A> {
+    let gradient = match (axis, keepdims) {
+        (Some(axis), false) => gradient.view().insert_axis(Axis(axis)),
+        _ => gradient.view(),
+    };
+    gradient.broadcast(shape).unwrap().to_owned()
+}
+
+/// Returns the number of elements each output of a reduction is computed from.
+fn lane_length<A>(x: &ArrayD<A>, axis: Option<usize>) -> usize {
+    match axis {
+        Some(axis) => x.len_of(Axis(axis)),
+        None => x.len(),
+    }
+}
+
+/// Returns the position of the first element of `lane` preferred by `better`.
+fn arg_best<A: Element>(lane: ArrayView1<A>, better: impl Fn(A, A) -> bool) -> usize {
+    let mut best = 0;
+    for (i, &x) in lane.iter().enumerate() {
+        if better(x, lane[best]) {
+            best = i;
+        }
+    }
+    best
+}
+
+impl<A: Element> Tensor<A> {
+    /// Sums the elements.
+    pub fn sum(&self, axis: Option<usize>, keepdims: bool) -> Self {
+        let value = reduce(&self.borrow().value, axis, keepdims, |lane| lane.sum());
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let x = &diff.children()[0].borrow().value;
+            vec![expand(&diff.gradient, x.shape(), axis, keepdims)]
+        })
+    }
+
+    /// Averages the elements.
+    pub fn mean(&self, axis: Option<usize>, keepdims: bool) -> Self {
+        let length = A::from(lane_length(&self.borrow().value, axis)).unwrap();
+        &self.sum(axis, keepdims) / length
+    }
+
+    /// Computes the variance of the elements, dividing the squared deviations by
+    /// `n - ddof`.
+    pub fn var(&self, axis: Option<usize>, keepdims: bool, ddof: A) -> Self {
+        let length = A::from(lane_length(&self.borrow().value, axis)).unwrap();
+        let value = reduce(&self.borrow().value, axis, keepdims, |lane| {
+            let mean = lane.sum() / length;
+            lane.fold(A::zero(), |acc, &x| acc + (x - mean) * (x - mean)) / (length - ddof)
+        });
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let x = &diff.children()[0].borrow().value;
+            let deviations = map_lanes(x, axis, |lane, mut output| {
+                let mean = lane.sum() / length;
+                output.zip_mut_with(&lane, |output, &x| *output = x - mean);
+            });
+            let scale = (A::one() + A::one()) / (length - ddof);
+            vec![expand(&diff.gradient, x.shape(), axis, keepdims) * deviations * scale]
+        })
+    }
+
+    /// Computes the standard deviation of the elements, dividing the squared
+    /// deviations by `n - ddof`.
+    pub fn std(&self, axis: Option<usize>, keepdims: bool, ddof: A) -> Self {
+        self.var(axis, keepdims, ddof).sqrt()
+    }
+
+    /// Finds the largest element. The gradient flows only to the first element
+    /// holding the maximum.
+    pub fn max(&self, axis: Option<usize>, keepdims: bool) -> Self {
+        self.select_best(axis, keepdims, |x, best| x > best)
+    }
+
+    /// Finds the smallest element. The gradient flows only to the first element
+    /// holding the minimum.
+    pub fn min(&self, axis: Option<usize>, keepdims: bool) -> Self {
+        self.select_best(axis, keepdims, |x, best| x < best)
+    }
+
+    fn select_best(
+        &self,
+        axis: Option<usize>,
+        keepdims: bool,
+        better: impl Fn(A, A) -> bool + Copy + 'static,
+    ) -> Self {
+        assert!(
+            lane_length(&self.borrow().value, axis) > 0,
+            "can't take the max or min over an empty axis ({axis:?})"
+        );
+        let value = reduce(&self.borrow().value, axis, keepdims, |lane| {
+            lane[arg_best(lane, better)]
+        });
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let x = &diff.children()[0].borrow().value;
+            let mask = map_lanes(x, axis, |lane, mut output| {
+                output[arg_best(lane, better)] = A::one();
+            });
+            vec![expand(&diff.gradient, x.shape(), axis, keepdims) * mask]
+        })
+    }
+
+    /// Multiplies the elements.
+    pub fn prod(&self, axis: Option<usize>, keepdims: bool) -> Self {
+        let value = reduce(&self.borrow().value, axis, keepdims, |lane| lane.product());
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let x = &diff.children()[0].borrow().value;
+            // The derivative with respect to each element is the product of all
+            // the others, built from prefix and suffix products so that zeros
+            // don't need special handling.
+            let others = map_lanes(x, axis, |lane, mut output| {
+                let mut prefix = A::one();
+                for (output, &x) in output.iter_mut().zip(lane.iter()) {
+                    *output = prefix;
+                    prefix = prefix * x;
+                }
+                let mut suffix = A::one();
+                for (output, &x) in output.iter_mut().zip(lane.iter()).rev() {
+                    *output = *output * suffix;
+                    suffix = suffix * x;
+                }
+            });
+            vec![expand(&diff.gradient, x.shape(), axis, keepdims) * others]
+        })
+    }
+
+    /// Computes `ln(sum(exp(x)))` without overflowing for large elements.
+    pub fn logsumexp(&self, axis: Option<usize>, keepdims: bool) -> Self {
+        let value = reduce(&self.borrow().value, axis, keepdims, |lane| {
+            let max = lane.fold(A::neg_infinity(), |max, &x| max.max(x));
+            if max.is_infinite() {
+                return max;
+            }
+            max + lane.fold(A::zero(), |acc, &x| acc + (x - max).exp()).ln()
+        });
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let x = &diff.children()[0].borrow().value;
+            let output = expand(&diff.value, x.shape(), axis, keepdims);
+            // Lanes with only -inf elements have no mass to route the
+            // gradient to, so they get none instead of `exp(-inf - -inf)`.
+            let mut softmax = x - &output;
+            softmax.zip_mut_with(&output, |softmax, &output| {
+                *softmax = if output == A::neg_infinity() {
+                    A::zero()
+                } else {
+                    softmax.exp()
+                };
+            });
+            vec![expand(&diff.gradient, x.shape(), axis, keepdims) * softmax]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, Array};
+
+    use super::*;
+    use crate::testing::check_tensor_gradients;
+
+    fn input() -> ArrayD<f64> {
+        array![
+            [[0.5, -1.5, 2.0], [1.0, 0.25, -0.75]],
+            [[1.5, 0.3, -2.5], [0.75, -1.25, 2.2]]
+        ]
+        .into_dyn()
+    }
+
+    const AXES: [Option<usize>; 4] = [None, Some(0), Some(1), Some(2)];
+
+    fn check_reduction(f: impl Fn(&Tensor<f64>, Option<usize>, bool) -> Tensor<f64>) {
+        for axis in AXES {
+            for keepdims in [false, true] {
+                // Weight the outputs so that each one gets a different gradient.
+                check_tensor_gradients(&[input()], |x| {
+                    let reduced = f(&x[0], axis, keepdims);
+                    let weights = Array::linspace(0.5, 2.0, reduced.borrow().value.len())
+                        .into_shape(reduced.borrow().value.raw_dim())
+                        .unwrap();
+                    &reduced * weights
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn shapes() {
+        let x = Tensor::new(input());
+        assert_eq!(x.sum(None, false).value().shape(), &[] as &[usize]);
+        assert_eq!(x.sum(None, true).value().shape(), &[1, 1, 1]);
+        assert_eq!(x.sum(Some(1), false).value().shape(), &[2, 3]);
+        assert_eq!(x.sum(Some(1), true).value().shape(), &[2, 1, 3]);
+    }
+
+    #[test]
+    fn values() {
+        let x = Tensor::new(array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn());
+        assert_eq!(
+            x.sum(Some(0), false).value(),
+            array![5.0, 7.0, 9.0].into_dyn()
+        );
+        assert_eq!(x.mean(Some(1), false).value(), array![2.0, 5.0].into_dyn());
+        assert_eq!(
+            x.var(Some(1), false, 0.0).value(),
+            array![2.0 / 3.0, 2.0 / 3.0].into_dyn()
+        );
+        assert_eq!(
+            x.std(Some(1), false, 1.0).value(),
+            array![1.0, 1.0].into_dyn()
+        );
+        assert_eq!(x.max(None, false).value(), arr0(6.0).into_dyn());
+        assert_eq!(
+            x.min(Some(0), true).value(),
+            array![[1.0, 2.0, 3.0]].into_dyn()
+        );
+        assert_eq!(
+            x.prod(Some(1), false).value(),
+            array![6.0, 120.0].into_dyn()
+        );
+    }
+
+    #[test]
+    fn max_routes_gradient_to_first_maximum() {
+        let x = Tensor::new(array![[3.0, 1.0, 3.0], [0.0, 2.0, 1.0]].into_dyn());
+        x.max(Some(1), false).backward();
+
+        assert_eq!(
+            x.gradient(),
+            array![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]].into_dyn()
+        );
+    }
+
+    #[test]
+    fn prod_with_zero() {
+        let x = Tensor::new(array![2.0, 0.0, 3.0].into_dyn());
+        x.prod(None, false).backward();
+
+        assert_eq!(x.gradient(), array![0.0, 6.0, 0.0].into_dyn());
+    }
+
+    #[test]
+    fn logsumexp_is_stable() {
+        let x = Tensor::new(array![1000.0, 1000.0].into_dyn());
+        let result = x.logsumexp(None, false);
+        result.backward();
+
+        assert!((result.value()[[]] - (1000.0 + 2.0_f64.ln())).abs() < 1e-9);
+        assert!(x.gradient().iter().all(|&g| (g - 0.5).abs() < 1e-9));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't take the max or min over an empty axis (Some(1))")]
+    fn max_needs_elements() {
+        Tensor::new(ArrayD::<f64>::zeros(vec![2, 0])).max(Some(1), false);
+    }
+
+    #[test]
+    fn logsumexp_of_masked_lanes() {
+        let inf = f64::INFINITY;
+        let x = Tensor::new(array![[-inf, -inf], [0.0, -inf]].into_dyn());
+        let result = x.logsumexp(Some(1), false);
+        result.backward();
+
+        assert_eq!(result.value(), array![-inf, 0.0].into_dyn());
+        assert_eq!(x.gradient(), array![[0.0, 0.0], [1.0, 0.0]].into_dyn());
+    }
+
+    #[test]
+    fn finite_differences_sum_and_mean() {
+        check_reduction(|x, axis, keepdims| x.sum(axis, keepdims));
+        check_reduction(|x, axis, keepdims| x.mean(axis, keepdims));
+    }
+
+    #[test]
+    fn finite_differences_var_and_std() {
+        check_reduction(|x, axis, keepdims| x.var(axis, keepdims, 0.0));
+        check_reduction(|x, axis, keepdims| x.std(axis, keepdims, 1.0));
+    }
+
+    #[test]
+    fn finite_differences_max_and_min() {
+        check_reduction(|x, axis, keepdims| x.max(axis, keepdims));
+        check_reduction(|x, axis, keepdims| x.min(axis, keepdims));
+    }
+
+    #[test]
+    fn finite_differences_prod_and_logsumexp() {
+        check_reduction(|x, axis, keepdims| x.prod(axis, keepdims));
+        check_reduction(|x, axis, keepdims| x.logsumexp(axis, keepdims));
+    }
+}
@@ -0,0 +1,272 @@
+//! Operations that rearrange the elements of tensors without changing them.
+
+use ndarray::{concatenate, stack, ArrayD, ArrayView, Axis, IxDyn, Slice};
+
+use super::{Element, Tensor};
+use crate::{Value, Var};
+
+/// Copies `x` into a new array of the given shape, in row-major order.
+fn reshaped<A: Element>(x: &ArrayD<A>, shape: &[usize]) -> ArrayD<A> {
+    x.as_standard_layout()
+        .into_owned()
+        .into_shape(IxDyn(shape))
+        .unwrap_or_else(|_| panic!("cannot reshape {:?} into {shape:?}", x.shape()))
+}
+
+impl<A: Element> Tensor<A> {
+    /// Rearranges the elements into the given shape, in row-major order. The
+    /// number of elements must stay the same.
+    pub fn reshape(&self, shape: &[usize]) -> Self {
+        Var::from_op(
+            reshaped(&self.borrow().value, shape),
+            vec![self.clone()],
+            |diff| {
+                let x = &diff.children()[0].borrow().value;
+                vec![reshaped(&diff.gradient, x.shape())]
+            },
+        )
+    }
+
+    /// Swaps two axes.
+    pub fn transpose(&self, a: usize, b: usize) -> Self {
+        let mut axes: Vec<_> = (0..self.borrow().value.ndim()).collect();
+        axes.swap(a, b);
+        self.permute_axes(&axes)
+    }
+
+    /// Reorders the axes, so that axis `i` of the result is axis `axes[i]` of
+    /// the input.
+    pub fn permute_axes(&self, axes: &[usize]) -> Self {
+        let value = self.value().permuted_axes(axes);
+        let mut inverse = vec![0; axes.len()];
+        for (i, &axis) in axes.iter().enumerate() {
+            inverse[axis] = i;
+        }
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            vec![diff.gradient.clone().permuted_axes(inverse.as_slice())]
+        })
+    }
+
+    /// Removes an axis of length one.
+    pub fn squeeze(&self, axis: usize) -> Self {
+        let mut shape = self.borrow().value.shape().to_vec();
+        assert_eq!(
+            shape[axis], 1,
+            "cannot squeeze axis {axis} of shape {shape:?}"
+        );
+        shape.remove(axis);
+        self.reshape(&shape)
+    }
+
+    /// Inserts an axis of length one at position `axis`.
+    pub fn unsqueeze(&self, axis: usize) -> Self {
+        let mut shape = self.borrow().value.shape().to_vec();
+        shape.insert(axis, 1);
+        self.reshape(&shape)
+    }
+
+    /// Repeats the elements to fill the given shape, following the NumPy
+    /// broadcasting rules.
+    pub fn broadcast_to(&self, shape: &[usize]) -> Self {
+        let value = {
+            let x = &self.borrow().value;
+            x.broadcast(shape)
+                .unwrap_or_else(|| panic!("cannot broadcast {:?} to {shape:?}", x.shape()))
+                .to_owned()
+        };
+
+        Var::from_op(value, vec![self.clone()], |diff| {
+            vec![diff.unbroadcast(diff.gradient.clone(), 0)]
+        })
+    }
+
+    /// Joins tensors along an existing axis.
+    pub fn concat(tensors: &[Tensor<A>], axis: usize) -> Self {
+        let value = {
+            let values: Vec<_> = tensors.iter().map(|x| x.value()).collect();
+            let views: Vec<_> = values.iter().map(ArrayD::view).collect();
+            concatenate(Axis(axis), &views).expect("cannot concatenate tensors")
+        };
+
+        Var::from_op(value, tensors.to_vec(), move |diff| {
+            let mut start = 0;
+            diff.children()
+                .iter()
+                .map(|child| {
+                    let length = child.borrow().value.len_of(Axis(axis));
+                    let slice = Slice::from(start..start + length);
+                    start += length;
+                    diff.gradient.slice_axis(Axis(axis), slice).to_owned()
+                })
+                .collect()
+        })
+    }
+
+    /// Joins tensors of the same shape along a new axis.
+    pub fn stack(tensors: &[Tensor<A>], axis: usize) -> Self {
+        let value = {
+            let values: Vec<_> = tensors.iter().map(|x| x.value()).collect();
+            let views: Vec<ArrayView<A, IxDyn>> = values.iter().map(ArrayD::view).collect();
+            stack(Axis(axis), &views).expect("cannot stack tensors")
+        };
+
+        Var::from_op(value, tensors.to_vec(), move |diff| {
+            (0..diff.children().len())
+                .map(|i| diff.gradient.index_axis(Axis(axis), i).to_owned())
+                .collect()
+        })
+    }
+
+    /// Takes `length` consecutive elements along `axis`, starting at `start`.
+    pub fn narrow(&self, axis: usize, start: usize, length: usize) -> Self {
+        let slice = Slice::from(start..start + length);
+        let value = self.borrow().value.slice_axis(Axis(axis), slice).to_owned();
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let mut gradient = diff.children()[0].borrow().value.zeros_like();
+            gradient
+                .slice_axis_mut(Axis(axis), slice)
+                .assign(&diff.gradient);
+            vec![gradient]
+        })
+    }
+
+    /// Splits the tensor along `axis` into pieces of the given lengths, which
+    /// must add up to the length of the axis.
+    pub fn split(&self, lengths: &[usize], axis: usize) -> Vec<Self> {
+        let total = self.borrow().value.len_of(Axis(axis));
+        assert_eq!(
+            lengths.iter().sum::<usize>(),
+            total,
+            "split lengths must add up to the length of axis {axis}"
+        );
+
+        let mut start = 0;
+        lengths
+            .iter()
+            .map(|&length| {
+                let piece = self.narrow(axis, start, length);
+                start += length;
+                piece
+            })
+            .collect()
+    }
+
+    /// Splits the tensor along `axis` into at most `chunks` pieces of length
+    /// `ceil(len / chunks)`, except for the last one, which is shorter if the
+    /// length of the axis isn't divisible by it. A length of 4 in 3 chunks
+    /// gives two pieces of 2.
+    pub fn chunk(&self, chunks: usize, axis: usize) -> Vec<Self> {
+        assert!(chunks > 0, "chunk count must be positive");
+        let total = self.borrow().value.len_of(Axis(axis));
+        let length = total.div_ceil(chunks);
+
+        let lengths: Vec<_> = (0..total)
+            .step_by(length.max(1))
+            .map(|start| length.min(total - start))
+            .collect();
+        self.split(&lengths, axis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence, weighted};
+
+    #[test]
+    fn shapes() {
+        let x = Tensor::new(sequence(&[2, 3, 4]));
+        assert_eq!(x.reshape(&[6, 4]).value().shape(), &[6, 4]);
+        assert_eq!(x.transpose(0, 2).value().shape(), &[4, 3, 2]);
+        assert_eq!(x.permute_axes(&[1, 2, 0]).value().shape(), &[3, 4, 2]);
+        assert_eq!(x.unsqueeze(1).value().shape(), &[2, 1, 3, 4]);
+        assert_eq!(x.unsqueeze(1).squeeze(1).value().shape(), &[2, 3, 4]);
+        assert_eq!(x.broadcast_to(&[5, 2, 3, 4]).value().shape(), &[5, 2, 3, 4]);
+        assert_eq!(
+            Tensor::concat(&[x.clone(), x.clone()], 1).value().shape(),
+            &[2, 6, 4]
+        );
+        assert_eq!(
+            Tensor::stack(&[x.clone(), x.clone()], 1).value().shape(),
+            &[2, 2, 3, 4]
+        );
+
+        let pieces = x.split(&[1, 3], 2);
+        assert_eq!(pieces[0].value().shape(), &[2, 3, 1]);
+        assert_eq!(pieces[1].value().shape(), &[2, 3, 3]);
+
+        let lengths: Vec<_> = x.chunk(3, 1).iter().map(|c| c.value().shape()[1]).collect();
+        assert_eq!(lengths, vec![1, 1, 1]);
+        let lengths: Vec<_> = x.chunk(3, 2).iter().map(|c| c.value().shape()[2]).collect();
+        assert_eq!(lengths, vec![2, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk count must be positive")]
+    fn chunk_needs_a_chunk() {
+        Tensor::new(sequence(&[2, 3])).chunk(0, 1);
+    }
+
+    #[test]
+    fn values() {
+        let x = Tensor::new(array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]].into_dyn());
+        assert_eq!(
+            x.reshape(&[3, 2]).value(),
+            array![[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]].into_dyn()
+        );
+        assert_eq!(
+            x.transpose(0, 1).value(),
+            array![[1.0, 4.0], [2.0, 5.0], [3.0, 6.0]].into_dyn()
+        );
+        assert_eq!(
+            x.transpose(0, 1).reshape(&[6]).value(),
+            array![1.0, 4.0, 2.0, 5.0, 3.0, 6.0].into_dyn()
+        );
+        assert_eq!(
+            x.narrow(1, 1, 2).value(),
+            array![[2.0, 3.0], [5.0, 6.0]].into_dyn()
+        );
+    }
+
+    #[test]
+    fn finite_differences_views() {
+        let inputs = [sequence(&[2, 3, 4])];
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].reshape(&[4, 6])));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].transpose(0, 2)));
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].permute_axes(&[1, 2, 0])));
+        check_tensor_gradients(&inputs, |x| {
+            weighted(&x[0].permute_axes(&[2, 0, 1]).reshape(&[24]))
+        });
+        check_tensor_gradients(&inputs, |x| weighted(&x[0].unsqueeze(3)));
+        check_tensor_gradients(&[sequence(&[2, 1, 3])], |x| weighted(&x[0].squeeze(1)));
+        check_tensor_gradients(&[sequence(&[3, 1])], |x| {
+            weighted(&x[0].broadcast_to(&[2, 3, 4]))
+        });
+    }
+
+    #[test]
+    fn finite_differences_joins() {
+        let inputs = [sequence(&[2, 3]), sequence(&[2, 3]).mapv(f64::sin)];
+        check_tensor_gradients(&inputs, |x| weighted(&Tensor::concat(x, 0)));
+        check_tensor_gradients(&inputs, |x| weighted(&Tensor::concat(x, 1)));
+        check_tensor_gradients(&inputs, |x| weighted(&Tensor::stack(x, 0)));
+        check_tensor_gradients(&inputs, |x| weighted(&Tensor::stack(x, 2)));
+    }
+
+    #[test]
+    fn finite_differences_splits() {
+        let inputs = [sequence(&[2, 5])];
+        check_tensor_gradients(&inputs, |x| {
+            let pieces = x[0].split(&[2, 3], 1);
+            &pieces[0].sum(None, false) * &pieces[1].sum(None, false).exp()
+        });
+        check_tensor_gradients(&inputs, |x| {
+            let pieces = x[0].chunk(2, 1);
+            &weighted(&pieces[0]).sum(None, false) - &pieces[1].max(None, false)
+        });
+    }
+}
@@ -0,0 +1,150 @@
+//! Softmax and log-softmax as single graph nodes.
+//!
+//! Both shift every lane by its maximum before exponentiating, so they never
+//! overflow, and backpropagate through their Jacobian directly instead of
+//! through a chain of elementwise nodes.
+
+use ndarray::{ArrayD, Axis};
+
+use super::{Element, Tensor};
+use crate::Var;
+
+/// Returns `x` minus the maximum of each lane along `axis`. Lanes with only
+/// -inf elements, such as fully masked rows, are left as they are rather than
+/// becoming `-inf - -inf`.
+fn shifted<A: Element>(x: &ArrayD<A>, axis: usize) -> ArrayD<A> {
+    let max = x
+        .map_axis(Axis(axis), |lane| {
+            let max = lane.fold(A::neg_infinity(), |max, &x| max.max(x));
+            if max == A::neg_infinity() {
+                A::zero()
+            } else {
+                max
+            }
+        })
+        .insert_axis(Axis(axis));
+    x - &max
+}
+
+/// Sums the exponentials of each lane of a shifted `x`. A fully masked lane
+/// has nothing to normalise, so it gets a sum of one and comes out as zero
+/// probabilities instead of `0 / 0`.
+fn normaliser<A: Element>(exponentials: &ArrayD<A>, axis: usize) -> ArrayD<A> {
+    sum_lanes(exponentials, axis).mapv(|sum| if sum == A::zero() { A::one() } else { sum })
+}
+
+/// Sums `x` along `axis`, keeping the axis with a length of one.
+fn sum_lanes<A: Element>(x: &ArrayD<A>, axis: usize) -> ArrayD<A> {
+    x.sum_axis(Axis(axis)).insert_axis(Axis(axis))
+}
+
+impl<A: Element> Tensor<A> {
+    /// Computes `exp(x) / sum(exp(x))` along `axis`, turning each lane into
+    /// probabilities.
+    pub fn softmax(&self, axis: usize) -> Self {
+        let exponentials = shifted(&self.borrow().value, axis).mapv(A::exp);
+        let value = &exponentials / &normaliser(&exponentials, axis);
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let y = &diff.value;
+            let projected = sum_lanes(&(&diff.gradient * y), axis);
+            vec![y * &(&diff.gradient - &projected)]
+        })
+    }
+
+    /// Computes `x - ln(sum(exp(x)))` along `axis`, the logarithm of
+    /// [`Tensor::softmax`], without ever taking the logarithm of a probability.
+    pub fn log_softmax(&self, axis: usize) -> Self {
+        let shifted = shifted(&self.borrow().value, axis);
+        let normaliser = normaliser(&shifted.mapv(A::exp), axis).mapv(A::ln);
+        let value = shifted - normaliser;
+
+        Var::from_op(value, vec![self.clone()], move |diff| {
+            let softmax = diff.value.mapv(A::exp);
+            let total = sum_lanes(&diff.gradient, axis);
+            vec![&diff.gradient - &(softmax * total)]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::testing::{check_tensor_gradients, sequence, weighted};
+
+    #[test]
+    fn values_are_probabilities() {
+        let x = Tensor::new(array![[1.0, 2.0, 3.0], [0.0, 0.0, 0.0]].into_dyn());
+        let softmax: ArrayD<f64> = x.softmax(1).value();
+
+        let expected = array![1.0_f64, 2.0, 3.0].mapv(f64::exp);
+        let expected = &expected / expected.sum();
+        assert!((&softmax.index_axis(Axis(0), 0) - &expected)
+            .iter()
+            .all(|d| d.abs() < 1e-12));
+        assert!(softmax
+            .index_axis(Axis(0), 1)
+            .iter()
+            .all(|p| (p - 1.0 / 3.0).abs() < 1e-12));
+
+        let log_softmax = x.log_softmax(1).value();
+        assert!((log_softmax.mapv(f64::exp) - softmax)
+            .iter()
+            .all(|d| d.abs() < 1e-12));
+    }
+
+    #[test]
+    fn large_inputs_do_not_overflow() {
+        let x = Tensor::new(array![[1000.0_f64, 0.0, -1000.0]].into_dyn());
+        assert_eq!(x.softmax(1).value(), array![[1.0, 0.0, 0.0]].into_dyn());
+        assert_eq!(
+            x.log_softmax(1).value(),
+            array![[0.0, -1000.0, -2000.0]].into_dyn()
+        );
+
+        let y = x.log_softmax(1);
+        y.backward();
+        assert!(x.gradient().iter().all(|g| g.is_finite()));
+    }
+
+    #[test]
+    fn fully_masked_lanes() {
+        let inf = f64::INFINITY;
+        let x = Tensor::new(array![[-inf, -inf], [0.0, -inf]].into_dyn());
+
+        let softmax = x.softmax(1);
+        assert_eq!(softmax.value(), array![[0.0, 0.0], [1.0, 0.0]].into_dyn());
+        weighted(&softmax).backward();
+        assert!(x.gradient().iter().all(|g| g.is_finite()));
+
+        x.zero_grad();
+        let log_softmax = x.log_softmax(1);
+        assert_eq!(
+            log_softmax.value(),
+            array![[-inf, -inf], [0.0, -inf]].into_dyn()
+        );
+        weighted(&log_softmax).backward();
+        assert!(x.gradient().iter().all(|g| g.is_finite()));
+    }
+
+    #[test]
+    fn single_node() {
+        let x = Tensor::new(sequence(&[2, 3]));
+        for y in [x.softmax(0), x.log_softmax(1)] {
+            let children = y.borrow().children().to_vec();
+            assert_eq!(children.len(), 1);
+            assert!(children[0].ptr_eq(&x));
+        }
+    }
+
+    #[test]
+    fn finite_differences() {
+        let inputs = [sequence(&[2, 3, 4]).mapv(|x| 3.0 * x.sin())];
+        for axis in 0..3 {
+            check_tensor_gradients(&inputs, |x| weighted(&x[0].softmax(axis)));
+            check_tensor_gradients(&inputs, |x| weighted(&x[0].log_softmax(axis)));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Helpers shared by the test modules.
+
+use ndarray::{Array, ArrayD};
+
+use crate::{Tensor, Var};
+
+/// Checks the gradients computed by `backward` for `f` at `inputs` against
+/// central finite differences.
+pub fn check_gradients(inputs: &[f64], f: impl Fn(&[Var<f64>]) -> Var<f64>) {
+    let evaluate = |inputs: &[f64]| {
+        let vars: Vec<_> = inputs.iter().map(|&x| Var::new(x)).collect();
+        f(&vars).value()
+    };
+
+    let vars: Vec<_> = inputs.iter().map(|&x| Var::new(x)).collect();
+    f(&vars).backward();
+
+    let step = 1e-6;
+    for (i, var) in vars.iter().enumerate() {
+        let mut above = inputs.to_vec();
+        above[i] += step;
+        let mut below = inputs.to_vec();
+        below[i] -= step;
+        let expected = (evaluate(&above) - evaluate(&below)) / (2.0 * step);
+
+        let actual = var.gradient();
+        assert!(
+            (actual - expected).abs() <= 1e-5 * (1.0 + expected.abs()),
+            "gradient of input {i}: expected {expected}, got {actual}"
+        );
+    }
+}
+
+/// Checks the gradients computed by `backward` for the tensor function `f` at
+/// `inputs` against central finite differences of the sum of its output.
+pub fn check_tensor_gradients(inputs: &[ArrayD<f64>], f: impl Fn(&[Tensor<f64>]) -> Tensor<f64>) {
+    let evaluate = |inputs: &[ArrayD<f64>]| {
+        let vars: Vec<_> = inputs.iter().map(|x| Var::new(x.clone())).collect();
+        f(&vars).value().sum()
+    };
+
+    let vars: Vec<_> = inputs.iter().map(|x| Var::new(x.clone())).collect();
+    f(&vars).backward();
+
+    let step = 1e-6;
+    for (i, var) in vars.iter().enumerate() {
+        let actual = var.gradient();
+        assert_eq!(actual.shape(), inputs[i].shape(), "shape of gradient {i}");
+
+        for (index, &actual) in actual.indexed_iter() {
+            let mut above = inputs.to_vec();
+            above[i][&index] += step;
+            let mut below = inputs.to_vec();
+            below[i][&index] -= step;
+            let expected = (evaluate(&above) - evaluate(&below)) / (2.0 * step);
+
+            assert!(
+                (actual - expected).abs() <= 1e-5 * (1.0 + expected.abs()),
+                "gradient of input {i} at {index:?}: expected {expected}, got {actual}"
+            );
+        }
+    }
+}
+
+/// Returns an array of the given shape filled with evenly spaced values.
+pub fn sequence(shape: &[usize]) -> ArrayD<f64> {
+    let len = shape.iter().product::<usize>();
+    Array::linspace(-1.0, 1.5, len).into_shape(shape).unwrap()
+}
+
+/// Weights the elements of `x` differently so that a wrong routing of the
+/// gradient shows up in the finite differences.
+pub fn weighted(x: &Tensor<f64>) -> Tensor<f64> {
+    let value = x.value();
+    let weights = Array::linspace(0.5, 2.0, value.len())
+        .into_shape(value.raw_dim())
+        .unwrap();
+    x * weights
+}
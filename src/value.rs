@@ -0,0 +1,131 @@
+//! Traits describing the types a [`Differentiable`](crate::Differentiable) can
+//! hold.
+
+use std::ops::{AddAssign, Mul};
+
+use ndarray::{ArrayD, Axis};
+use num::{Float, One, Zero};
+
+/// A type that can be held in a [`Differentiable`](crate::Differentiable):
+/// either a scalar or an n-dimensional array of scalars.
+pub trait Value: Clone + for<'a> AddAssign<&'a Self> {
+    /// Returns zero with the same shape as `self`.
+    fn zeros_like(&self) -> Self;
+
+    /// Returns one with the same shape as `self`.
+    fn ones_like(&self) -> Self;
+
+    /// Sums `self` down to the shape of `target`, which `self` must have been
+    /// broadcast from. This is how gradients of broadcasting operations are
+    /// routed back to their smaller operands.
+    fn sum_to(self, target: &Self) -> Self;
+}
+
+/// A [`Value`] made of floating point elements that can be transformed one at
+/// a time, which is what the elementwise functions are built on.
+pub trait Elementwise: Value + Mul<Output = Self> {
+    /// The type of the elements.
+    type Elem: Float + 'static;
+
+    /// Applies `f` to every element.
+    fn map(&self, f: impl Fn(Self::Elem) -> Self::Elem) -> Self;
+
+    /// Applies `f` to every pair of elements of `self` and `other`, which must
+    /// have the same shape.
+    fn zip_map(&self, other: &Self, f: impl Fn(Self::Elem, Self::Elem) -> Self::Elem) -> Self;
+}
+
+/// Converts an `f64` constant to `T`.
+pub(crate) fn constant<T: Float>(value: f64) -> T {
+    T::from(value).unwrap()
+}
+
+macro_rules! impl_value_for_scalar {
+    ($($t:ty),*) => {$(
+        impl Value for $t {
+            fn zeros_like(&self) -> Self {
+                <$t>::zero()
+            }
+
+            fn ones_like(&self) -> Self {
+                <$t>::one()
+            }
+
+            fn sum_to(self, _target: &Self) -> Self {
+                self
+            }
+        }
+    )*};
+}
+
+// Unsigned integers are left out: negating or subtracting gradients, as the
+// backward rules of `-` and `Sub` do, would overflow them.
+impl_value_for_scalar!(f32, f64, i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_elementwise_for_scalar {
+    ($($t:ty),*) => {$(
+        impl Elementwise for $t {
+            type Elem = $t;
+
+            fn map(&self, f: impl Fn($t) -> $t) -> Self {
+                f(*self)
+            }
+
+            fn zip_map(&self, other: &Self, f: impl Fn($t, $t) -> $t) -> Self {
+                f(*self, *other)
+            }
+        }
+    )*};
+}
+
+impl_elementwise_for_scalar!(f32, f64);
+
+impl<A> Value for ArrayD<A>
+where
+    A: Clone + Zero + One + AddAssign,
+{
+    fn zeros_like(&self) -> Self {
+        ArrayD::zeros(self.raw_dim())
+    }
+
+    fn ones_like(&self) -> Self {
+        ArrayD::ones(self.raw_dim())
+    }
+
+    fn sum_to(self, target: &Self) -> Self {
+        if self.shape() == target.shape() {
+            return self;
+        }
+
+        // Broadcasting prepends axes, so sum those away first...
+        let mut result = self;
+        while result.ndim() > target.ndim() {
+            result = result.sum_axis(Axis(0));
+        }
+        // ...and then the axes that were stretched from a length of one.
+        for (axis, &length) in target.shape().iter().enumerate() {
+            if length == 1 && result.shape()[axis] != 1 {
+                result = result.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+            }
+        }
+
+        result
+    }
+}
+
+impl<A> Elementwise for ArrayD<A>
+where
+    A: Float + AddAssign + 'static,
+{
+    type Elem = A;
+
+    fn map(&self, f: impl Fn(A) -> A) -> Self {
+        self.mapv(f)
+    }
+
+    fn zip_map(&self, other: &Self, f: impl Fn(A, A) -> A) -> Self {
+        let mut result = self.clone();
+        result.zip_mut_with(other, |x, &y| *x = f(*x, y));
+        result
+    }
+}